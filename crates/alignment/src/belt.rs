@@ -0,0 +1,299 @@
+use std::cmp::Ordering;
+
+use crate::board::Board;
+use crate::distance::DistanceTable;
+use crate::goal::{face_dims, join, Axis, Goal};
+use crate::model::{Direction3D, Pos3D, Size3D};
+use crate::solver::{search, Plan, Tokens, EMPTY};
+
+/// A closed ring of surface cells around an axis: every cell `layer` cells along the axis that
+/// lies on a face parallel to it.
+///
+/// The box needs no hole for its belts to turn, which makes them a puzzle of their own on the same
+/// surface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Belt {
+    pub axis: Axis,
+    pub layer: u8,
+}
+
+impl Belt {
+    pub fn new(axis: Axis, layer: u8) -> Self {
+        Belt { axis, layer }
+    }
+
+    /// Every belt of a box of `size`, around X, then Y, then Z.
+    pub fn all(size: Size3D) -> Vec<Belt> {
+        Axis::ALL
+            .into_iter()
+            .flat_map(|axis| (0..span(size, axis)).map(move |layer| Belt::new(axis, layer)))
+            .collect()
+    }
+
+    /// The cells of the belt in order, running counterclockwise seen from the positive end of the
+    /// axis; empty if the layer is off the box.
+    pub fn cells(self, size: Size3D) -> Vec<Pos3D> {
+        if span(size, self.axis) <= self.layer {
+            return vec![];
+        }
+        let (u, v) = face_dims(size, self.axis);
+        let ring = (0..u)
+            .map(|b| (b, 0))
+            .chain((1..v).map(|c| (u - 1, c)))
+            .chain((0..u - 1).rev().map(|b| (b, v - 1)))
+            .chain((1..v - 1).rev().map(|c| (0, c)));
+        ring.map(|(b, c)| join(self.layer, b, c, self.axis))
+            .collect()
+    }
+}
+
+/// A belt turned one cell along its order, or back against it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Turn {
+    pub belt: Belt,
+    pub forward: bool,
+}
+
+impl Turn {
+    pub fn new(belt: Belt, forward: bool) -> Self {
+        Turn { belt, forward }
+    }
+
+    #[must_use]
+    pub fn invert(self) -> Self {
+        Turn::new(self.belt, !self.forward)
+    }
+
+    /// The axis, layer and way, such as `z1+` for the belt one cell up the Z axis turned forward.
+    pub fn notation(self) -> String {
+        let way = if self.forward { '+' } else { '-' };
+        format!("{}{}{}", self.belt.axis.name(), self.belt.layer, way)
+    }
+
+    pub fn parse(value: &str) -> Option<Turn> {
+        let value = value.trim();
+        let axis = Axis::ALL
+            .into_iter()
+            .find(|axis| value.starts_with(axis.name()))?;
+        let forward = match value.chars().last()? {
+            '+' => true,
+            '-' => false,
+            _ => return None,
+        };
+        let layer = value.get(1..value.len() - 1)?.parse().ok()?;
+        Some(Turn::new(Belt::new(axis, layer), forward))
+    }
+
+    /// Every cell of the belt with the cell its content moves on to and the way it steps.
+    pub fn steps(self, size: Size3D) -> Vec<(Pos3D, Pos3D, Direction3D)> {
+        let cells = self.belt.cells(size);
+        let n = cells.len();
+        (0..n)
+            .map(|k| {
+                let (from, to) = if self.forward {
+                    (cells[k], cells[(k + 1) % n])
+                } else {
+                    (cells[(k + 1) % n], cells[k])
+                };
+                (from, to, toward(from, to))
+            })
+            .collect()
+    }
+}
+
+/// Cells a box of `size` has along `axis`.
+fn span(size: Size3D, axis: Axis) -> u8 {
+    match axis {
+        Axis::X => size.x(),
+        Axis::Y => size.y(),
+        Axis::Z => size.z(),
+    }
+}
+
+/// The way from `from` to the neighbouring cell `to`.
+fn toward(from: Pos3D, to: Pos3D) -> Direction3D {
+    match (
+        to.x().cmp(&from.x()),
+        to.y().cmp(&from.y()),
+        to.z().cmp(&from.z()),
+    ) {
+        (Ordering::Less, _, _) => Direction3D::XNega,
+        (Ordering::Greater, _, _) => Direction3D::XPosi,
+        (_, Ordering::Less, _) => Direction3D::YNega,
+        (_, Ordering::Greater, _) => Direction3D::YPosi,
+        (_, _, Ordering::Less) => Direction3D::ZNega,
+        _ => Direction3D::ZPosi,
+    }
+}
+
+/// A* over boards toward a goal, one belt turn per move.
+///
+/// A turn carries every cube on its belt one cell on, so the estimate is the larger of the
+/// furthest cube's geodesic distance and the sum of them all shared out over the longest belt. It
+/// never overstates the turns left and drops by at most one with a turn, so the first solved
+/// board taken off the open list is reached by the fewest turns.
+///
+/// Belts with a wall or a pinned cube of the board searched from never turn.
+pub struct BeltSolver {
+    size: Size3D,
+    tokens: Tokens,
+}
+
+impl BeltSolver {
+    /// Solves back to every cube at home.
+    pub fn new(table: &DistanceTable) -> Self {
+        BeltSolver::with_goal(table, &Goal::identity(table.size()))
+    }
+
+    pub fn with_goal(table: &DistanceTable, goal: &Goal) -> Self {
+        BeltSolver {
+            size: table.size(),
+            tokens: Tokens::new(table, goal),
+        }
+    }
+
+    /// Searches from `board` until it is solved or `budget` boards have been expanded.
+    pub fn solve(&self, board: &Board, budget: usize) -> Plan<Turn> {
+        let tokens = &self.tokens;
+        let blocked = board.blocked();
+        let rings: Vec<(Belt, Vec<usize>)> = Belt::all(self.size)
+            .into_iter()
+            .map(|belt| (belt, belt.cells(self.size)))
+            .filter(|(_, cells)| cells.iter().all(|pos| !blocked.contains(pos)))
+            .map(|(belt, cells)| (belt, cells.iter().map(|pos| tokens.index[pos]).collect()))
+            .collect();
+        let longest = rings
+            .iter()
+            .map(|(_, ring)| ring.len() as u32)
+            .max()
+            .unwrap_or(1);
+
+        let expand = |cells: &[u16], disorder: u32, next: &mut Vec<(Turn, Vec<u16>, u32)>| {
+            for (belt, ring) in rings.iter() {
+                let n = ring.len();
+                for forward in [true, false] {
+                    let mut after = cells.to_vec();
+                    let mut disorder = disorder;
+                    for k in 0..n {
+                        let (from, to) = if forward {
+                            (ring[k], ring[(k + 1) % n])
+                        } else {
+                            (ring[(k + 1) % n], ring[k])
+                        };
+                        let token = cells[from];
+                        after[to] = token;
+                        if token != EMPTY {
+                            let token = token as usize;
+                            disorder =
+                                disorder + tokens.steps(to, token) - tokens.steps(from, token);
+                        }
+                    }
+                    next.push((Turn::new(*belt, forward), after, disorder));
+                }
+            }
+        };
+        let root = tokens.encode(board);
+        let disorder = tokens.disorder(&root);
+        search(
+            root,
+            disorder,
+            budget,
+            |cells, disorder| tokens.estimate(cells, disorder, longest),
+            expand,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::goal::GoalShape;
+    use crate::slide::move_one;
+
+    #[test]
+    fn belts_ring_the_box() {
+        let size = Size3D::new(3, 4, 5);
+        let belts = Belt::all(size);
+        assert_eq!(belts.len(), 3 + 4 + 5);
+        belts.iter().for_each(|belt| {
+            let cells = belt.cells(size);
+            let (u, v) = face_dims(size, belt.axis);
+            assert_eq!(cells.len(), 2 * (u + v) as usize - 4);
+            // Every step of a turn leads to a neighbour on the surface.
+            Turn::new(*belt, true)
+                .steps(size)
+                .into_iter()
+                .for_each(|(from, to, d)| {
+                    assert!(from.on_face(size) && to.on_face(size));
+                    assert_eq!(move_one(from, size, d), Some(to));
+                });
+        });
+        let z1 = Belt::new(Axis::Z, 1).cells(size);
+        assert_eq!(
+            &z1[..4],
+            &[
+                Pos3D::new(0, 0, 1),
+                Pos3D::new(1, 0, 1),
+                Pos3D::new(2, 0, 1),
+                Pos3D::new(2, 1, 1),
+            ]
+        );
+        assert!(Belt::new(Axis::X, 3).cells(size).is_empty());
+
+        let turn = Turn::new(Belt::new(Axis::Y, 2), false);
+        assert_eq!(turn.notation(), "y2-");
+        assert_eq!(Turn::parse("y2-"), Some(turn));
+        assert_eq!(Turn::parse("y2"), None);
+        assert_eq!(Turn::parse("w2+"), None);
+    }
+
+    #[test]
+    fn turns_go_round_and_back() {
+        let size = Size3D::new(3, 3, 4);
+        let solved = Board::solved(size);
+        let mut board = solved.clone();
+        let turn = Turn::new(Belt::new(Axis::Z, 1), true);
+        assert!(board.turn(turn));
+        assert_eq!(board.at_home(), solved.parts().len() - 8);
+        assert!(board.turn(turn.invert()));
+        assert_eq!(board, solved);
+        (0..8).for_each(|_| assert!(board.turn(turn)));
+        assert_eq!(board, solved);
+        assert!(!board.turn(Turn::new(Belt::new(Axis::X, 3), true)));
+
+        let pins = [Pos3D::new(0, 0, 1)].into_iter().collect();
+        let mut pinned = solved.with_obstacles(Default::default(), pins).unwrap();
+        assert!(!pinned.turn(turn));
+        assert!(pinned.turn(Turn::new(Belt::new(Axis::Z, 2), true)));
+        assert_eq!(pinned.turns().len(), 2 * (3 + 3 + 4 - 3));
+    }
+
+    #[test]
+    fn solves_short_turn_scrambles() {
+        let size = Size3D::new(3, 3, 3);
+        let table = DistanceTable::new(size);
+        let solved = Board::solved(size);
+        let mut board = solved.clone();
+        let scramble = [
+            Turn::new(Belt::new(Axis::Z, 0), true),
+            Turn::new(Belt::new(Axis::X, 1), false),
+            Turn::new(Belt::new(Axis::Y, 2), true),
+        ];
+        scramble
+            .iter()
+            .for_each(|turn| assert!(board.turn(*turn), "{:?}", turn));
+
+        let plan = BeltSolver::new(&table).solve(&board, 50_000);
+        assert!(plan.solved);
+        assert_eq!(plan.moves.len(), scramble.len());
+        plan.moves
+            .iter()
+            .for_each(|turn| assert!(board.turn(*turn), "{:?}", turn));
+        assert_eq!(board, solved);
+
+        // Toward another goal the box starts out of place.
+        let goal = GoalShape::Mirror(Axis::X).goal(size).unwrap();
+        let plan = BeltSolver::with_goal(&table, &goal).solve(&board, 1_000);
+        assert!(!plan.moves.is_empty());
+    }
+}
@@ -0,0 +1,369 @@
+use getset::*;
+use std::collections::{HashMap, HashSet};
+use std::iter::once;
+use strum::IntoEnumIterator;
+
+use crate::belt::{Belt, Turn};
+use crate::distance::DistanceTable;
+use crate::model::{generate_surfaces, Cube, Direction3D, Pos3D, Size3D};
+use crate::slide::{line, move_one};
+
+/// One move: the cube at `src` moves one cell toward `direction`, pushing the cubes in front of it
+/// one cell on along the surface line, so that `cubes` cubes shift toward the hole at its end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Move {
+    pub src: Pos3D,
+    pub direction: Direction3D,
+    pub cubes: u8,
+}
+
+impl Move {
+    /// A slide of the one cube at `src`.
+    pub fn new(src: Pos3D, direction: Direction3D) -> Self {
+        Move::line(src, direction, 1)
+    }
+
+    pub fn line(src: Pos3D, direction: Direction3D, cubes: u8) -> Self {
+        Move {
+            src,
+            direction,
+            cubes,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Getters, CopyGetters)]
+pub struct Board {
+    #[getset(get_copy = "pub")]
+    size: Size3D,
+    #[getset(get = "pub")]
+    parts: HashMap<Pos3D, Cube>,
+    /// Cells blocked for good: never a hole, and no cube may slide into them.
+    #[getset(get = "pub")]
+    walls: HashSet<Pos3D>,
+    /// Cells whose cube is pinned in place.
+    #[getset(get = "pub")]
+    pins: HashSet<Pos3D>,
+}
+
+impl Board {
+    pub fn new(size: Size3D, parts: HashMap<Pos3D, Cube>) -> Self {
+        Board {
+            size,
+            parts,
+            walls: HashSet::new(),
+            pins: HashSet::new(),
+        }
+    }
+
+    pub fn solved(size: Size3D) -> Self {
+        let parts = generate_surfaces(size)
+            .into_iter()
+            .map(|pos| (pos, Cube::new(pos)))
+            .collect();
+        Board::new(size, parts)
+    }
+
+    pub fn with_hole(size: Size3D, hole: Pos3D) -> Self {
+        let mut board = Board::solved(size);
+        board.parts.remove(&hole);
+        board
+    }
+
+    /// The same board with walls on `walls`, taking away any cube there, and the cubes on `pins`
+    /// pinned; nothing if a cell is off the surface, is both, or a pin has no cube to hold.
+    pub fn with_obstacles(mut self, walls: HashSet<Pos3D>, pins: HashSet<Pos3D>) -> Option<Board> {
+        let on_surface = |pos: &Pos3D| pos.on_face(self.size);
+        if !walls.iter().all(on_surface) || !walls.is_disjoint(&pins) {
+            return None;
+        }
+        walls.iter().for_each(|pos| {
+            self.parts.remove(pos);
+        });
+        if !pins.iter().all(|pos| self.parts.contains_key(pos)) {
+            return None;
+        }
+        self.walls = walls;
+        self.pins = pins;
+        Some(self)
+    }
+
+    /// Walls and pinned cubes, the cells no cube can move through.
+    pub fn blocked(&self) -> HashSet<Pos3D> {
+        self.walls.union(&self.pins).copied().collect()
+    }
+
+    pub fn get(&self, pos: Pos3D) -> Option<Cube> {
+        self.parts.get(&pos).copied()
+    }
+
+    pub fn holes(&self) -> Vec<Pos3D> {
+        generate_surfaces(self.size)
+            .into_iter()
+            .filter(|pos| !self.parts.contains_key(pos) && !self.walls.contains(pos))
+            .collect()
+    }
+
+    /// Slides the cube at `src` toward `d`, unless it is pinned or would run into a wall.
+    pub fn slide(&mut self, src: Pos3D, d: Direction3D) -> bool {
+        self.apply(Move::new(src, d))
+    }
+
+    pub fn apply(&mut self, mv: Move) -> bool {
+        match self.shifts(mv) {
+            Some(shifts) => {
+                // From the hole back, so every cube moves into a cell already left.
+                shifts.into_iter().rev().for_each(|(from, to, _)| {
+                    if let Some(cube) = self.parts.remove(&from) {
+                        self.parts.insert(to, cube);
+                    }
+                });
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Every cube `mv` shifts, from the one at `src` to the one next to the hole, with the cell it
+    /// leaves, the cell it enters and the way it steps; nothing if a cube is missing or pinned, or
+    /// the line does not end in a hole.
+    pub fn shifts(&self, mv: Move) -> Option<Vec<(Pos3D, Pos3D, Direction3D)>> {
+        let path = line(mv.src, self.size, mv.direction);
+        let count = mv.cubes as usize;
+        // The first step runs straight; a line only bends further on.
+        if count == 0 || path.len() < count || path[0].1 != mv.direction {
+            return None;
+        }
+        let (hole, _) = path[count - 1];
+        if self.parts.contains_key(&hole) || self.walls.contains(&hole) {
+            return None;
+        }
+        let froms = once(mv.src).chain(path.iter().map(|(pos, _)| *pos));
+        froms
+            .zip(path.iter().take(count))
+            .map(|(from, (to, d))| {
+                (self.parts.contains_key(&from) && !self.pins.contains(&from))
+                    .then_some((from, *to, *d))
+            })
+            .collect()
+    }
+
+    /// Every slide into one of the holes.
+    pub fn moves(&self) -> Vec<Move> {
+        self.holes()
+            .into_iter()
+            .flat_map(|hole| {
+                Direction3D::iter().filter_map(move |d| {
+                    move_one(hole, self.size, d)
+                        .filter(|src| self.parts.contains_key(src) && !self.pins.contains(src))
+                        .map(|src| Move::new(src, d.invert()))
+                })
+            })
+            .collect()
+    }
+
+    /// Every move shifting a line of one or more cubes into a hole.
+    pub fn line_moves(&self) -> Vec<Move> {
+        let movable = |pos: &Pos3D| self.parts.contains_key(pos) && !self.pins.contains(pos);
+        self.parts
+            .keys()
+            .filter(|src| movable(src))
+            .flat_map(|src| {
+                Direction3D::iter().filter_map(move |d| {
+                    let path = line(*src, self.size, d);
+                    if path.first().map(|(_, first)| *first) != Some(d) {
+                        return None;
+                    }
+                    let end = path.iter().position(|(pos, _)| !movable(pos))?;
+                    let hole = path[end].0;
+                    // A line longer than a move can count is never offered.
+                    let cubes = u8::try_from(end + 1).ok()?;
+                    (!self.parts.contains_key(&hole) && !self.walls.contains(&hole))
+                        .then(|| Move::line(*src, d, cubes))
+                })
+            })
+            .collect()
+    }
+
+    /// Turns a belt one cell along, unless it is off the box or a wall or a pinned cube lies on it.
+    pub fn turn(&mut self, turn: Turn) -> bool {
+        let steps = turn.steps(self.size);
+        let stuck = steps
+            .iter()
+            .any(|(pos, _, _)| self.walls.contains(pos) || self.pins.contains(pos));
+        if steps.is_empty() || stuck {
+            return false;
+        }
+        let moved: Vec<(Pos3D, Cube)> = steps
+            .iter()
+            .filter_map(|(from, to, _)| self.parts.remove(from).map(|cube| (*to, cube)))
+            .collect();
+        self.parts.extend(moved);
+        true
+    }
+
+    /// Every turn of a belt clear of walls and pinned cubes.
+    pub fn turns(&self) -> Vec<Turn> {
+        let blocked = self.blocked();
+        Belt::all(self.size)
+            .into_iter()
+            .filter(|belt| {
+                belt.cells(self.size)
+                    .iter()
+                    .all(|pos| !blocked.contains(pos))
+            })
+            .flat_map(|belt| [Turn::new(belt, true), Turn::new(belt, false)])
+            .collect()
+    }
+
+    /// Number of cubes sitting on their home cell.
+    pub fn at_home(&self) -> usize {
+        self.parts
+            .iter()
+            .filter(|(pos, cube)| **pos == cube.home())
+            .count()
+    }
+
+    /// Whether every cube is back on its home cell.
+    pub fn is_solved(&self) -> bool {
+        self.at_home() == self.parts.len()
+    }
+
+    /// Sum of the geodesic distances of every cube from its home.
+    pub fn disorder(&self, table: &DistanceTable) -> u32 {
+        self.parts
+            .iter()
+            .filter_map(|(pos, cube)| table.get(*pos, cube.home()))
+            .map(u32::from)
+            .sum()
+    }
+
+    /// The cube at `src` and where it would end up, if it can slide toward `d`.
+    pub fn destination(&self, src: Pos3D, d: Direction3D) -> Option<(Cube, Pos3D)> {
+        let cube = self.get(src).filter(|_| !self.pins.contains(&src))?;
+        move_one(src, self.size, d)
+            .filter(|next| {
+                next.on_face(self.size)
+                    && !self.parts.contains_key(next)
+                    && !self.walls.contains(next)
+            })
+            .map(|next| (cube, next))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn board_slides() {
+        let size = Size3D::new(3, 4, 5);
+        let hole = Pos3D::new(2, 3, 4);
+        let mut board = Board::with_hole(size, hole);
+        assert_eq!(board.holes(), vec![hole]);
+        assert!(board.is_solved());
+
+        let src = Pos3D::new(2, 2, 4);
+        let cube = board.get(src).unwrap();
+        assert_eq!(board.destination(src, Direction3D::XNega), None);
+        assert_eq!(
+            board.destination(src, Direction3D::YPosi),
+            Some((cube, hole))
+        );
+
+        assert!(board.slide(src, Direction3D::YPosi));
+        assert_eq!(board.get(hole), Some(cube));
+        assert_eq!(board.holes(), vec![src]);
+        assert_ne!(board, Board::with_hole(size, src));
+        assert_eq!(board.at_home(), board.parts().len() - 1);
+        assert!(!board.is_solved());
+        assert_eq!(board.disorder(&DistanceTable::new(size)), 1);
+
+        assert_eq!(board.moves().len(), 4);
+        assert!(board.moves().contains(&Move::new(hole, Direction3D::YNega)));
+        assert!(board.apply(Move::new(hole, Direction3D::YNega)));
+        assert_eq!(board, Board::with_hole(size, hole));
+    }
+
+    #[test]
+    fn lines_shift_toward_the_hole() {
+        let size = Size3D::new(3, 4, 5);
+        let hole = Pos3D::new(2, 1, 2);
+        let mut board = Board::with_hole(size, hole);
+
+        // From the middle of the z- face, over the edge and up the x+ face to the hole.
+        let src = Pos3D::new(1, 1, 0);
+        let mv = Move::line(src, Direction3D::XPosi, 3);
+        let shifts = board.shifts(mv).unwrap();
+        assert_eq!(
+            shifts,
+            vec![
+                (src, Pos3D::new(2, 1, 0), Direction3D::XPosi),
+                (Pos3D::new(2, 1, 0), Pos3D::new(2, 1, 1), Direction3D::ZPosi),
+                (Pos3D::new(2, 1, 1), hole, Direction3D::ZPosi),
+            ]
+        );
+        assert_eq!(board.shifts(Move::line(src, Direction3D::XPosi, 2)), None);
+        assert_eq!(board.shifts(Move::line(src, Direction3D::XPosi, 4)), None);
+        assert!(board.line_moves().contains(&mv));
+        assert!(board
+            .line_moves()
+            .contains(&Move::new(Pos3D::new(2, 1, 1), Direction3D::ZPosi)));
+
+        let before = board.clone();
+        assert!(board.apply(mv));
+        assert_eq!(board.holes(), vec![src]);
+        assert_eq!(board.get(hole), before.get(Pos3D::new(2, 1, 1)));
+        assert_eq!(board.get(Pos3D::new(2, 1, 0)), before.get(src));
+        assert_eq!(board.at_home(), board.parts().len() - 3);
+
+        // The pushed cube must not turn on its own first step.
+        let corner = Board::with_hole(size, Pos3D::new(2, 0, 1));
+        assert_eq!(
+            corner.shifts(Move::new(Pos3D::new(2, 0, 0), Direction3D::XPosi)),
+            None
+        );
+    }
+
+    #[test]
+    fn obstacles_hold() {
+        let size = Size3D::new(3, 4, 5);
+        let hole = Pos3D::new(1, 1, 0);
+        let wall = Pos3D::new(0, 1, 0);
+        let pin = Pos3D::new(1, 2, 0);
+        let walls: HashSet<Pos3D> = [wall].into_iter().collect();
+        let pins: HashSet<Pos3D> = [pin].into_iter().collect();
+        let mut board = Board::with_hole(size, hole)
+            .with_obstacles(walls.clone(), pins.clone())
+            .unwrap();
+        assert_eq!(board.holes(), vec![hole]);
+        assert_eq!(board.get(wall), None);
+        assert_eq!(board.blocked().len(), 2);
+
+        // Only the cubes below and to the right may fill the hole.
+        let mut moves = board.moves();
+        moves.sort_by_key(|mv| mv.direction as u8);
+        assert_eq!(
+            moves,
+            vec![
+                Move::new(Pos3D::new(2, 1, 0), Direction3D::XNega),
+                Move::new(Pos3D::new(1, 0, 0), Direction3D::YPosi),
+            ]
+        );
+        assert!(!board.slide(pin, Direction3D::YNega));
+        assert!(board.slide(Pos3D::new(1, 0, 0), Direction3D::YPosi));
+        assert!(!board.slide(hole, Direction3D::XNega));
+
+        let cube = Board::solved(size);
+        assert!(cube.clone().with_obstacles(pins.clone(), pins).is_none());
+        let inside: HashSet<Pos3D> = [Pos3D::new(1, 1, 1)].into_iter().collect();
+        assert!(cube
+            .clone()
+            .with_obstacles(inside, HashSet::new())
+            .is_none());
+        let empty: HashSet<Pos3D> = [hole].into_iter().collect();
+        assert!(Board::with_hole(size, hole)
+            .with_obstacles(HashSet::new(), empty)
+            .is_none());
+    }
+}
@@ -0,0 +1,129 @@
+use getset::*;
+use std::collections::{HashMap, HashSet, VecDeque};
+use strum::IntoEnumIterator;
+
+use crate::model::{generate_surfaces, Direction3D, Pos3D, Size3D};
+use crate::slide::move_one;
+
+/// Shortest number of slides between every pair of surface cells, walking over the surface only
+/// and around any blocked cells.
+#[derive(Debug, Clone, PartialEq, Eq, Getters, CopyGetters)]
+pub struct DistanceTable {
+    #[getset(get_copy = "pub")]
+    size: Size3D,
+    /// Cells no walk enters or leaves, such as walls and pinned cubes.
+    #[getset(get = "pub")]
+    blocked: HashSet<Pos3D>,
+    index: HashMap<Pos3D, usize>,
+    steps: Vec<u16>,
+}
+
+impl DistanceTable {
+    pub fn new(size: Size3D) -> Self {
+        DistanceTable::with_blocked(size, HashSet::new())
+    }
+
+    pub fn with_blocked(size: Size3D, blocked: HashSet<Pos3D>) -> Self {
+        let cells = generate_surfaces(size);
+        let index: HashMap<Pos3D, usize> =
+            cells.iter().enumerate().map(|(i, pos)| (*pos, i)).collect();
+        let n = cells.len();
+
+        let mut steps = vec![u16::MAX; n * n];
+        cells.iter().enumerate().for_each(|(i, start)| {
+            let row = &mut steps[(i * n)..((i + 1) * n)];
+            row[i] = 0;
+            if blocked.contains(start) {
+                return;
+            }
+            let mut queue = VecDeque::from(vec![*start]);
+            while let Some(pos) = queue.pop_front() {
+                let next_steps = row[index[&pos]] + 1;
+                Direction3D::iter()
+                    .filter_map(|d| move_one(pos, size, d))
+                    .filter(|next| next.on_face(size) && !blocked.contains(next))
+                    .for_each(|next| {
+                        let j = index[&next];
+                        if row[j] == u16::MAX {
+                            row[j] = next_steps;
+                            queue.push_back(next);
+                        }
+                    });
+            }
+        });
+
+        DistanceTable {
+            size,
+            blocked,
+            index,
+            steps,
+        }
+    }
+
+    /// The longest of all the shortest walks, so no cube is ever further than this from home.
+    pub fn diameter(&self) -> u16 {
+        self.steps
+            .iter()
+            .copied()
+            .filter(|steps| *steps != u16::MAX)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Geodesic distance in slides, or `None` if either cell is not on the surface or no walk
+    /// joins them.
+    pub fn get(&self, a: Pos3D, b: Pos3D) -> Option<u16> {
+        let i = self.index.get(&a)?;
+        let j = self.index.get(&b)?;
+        Some(self.steps[i * self.index.len() + j]).filter(|steps| *steps != u16::MAX)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn distances() {
+        let size = Size3D::new(3, 4, 5);
+        let table = DistanceTable::new(size);
+
+        let cells = generate_surfaces(size);
+        for a in cells.iter() {
+            assert_eq!(table.get(*a, *a), Some(0));
+            for b in cells.iter() {
+                assert_eq!(table.get(*a, *b), table.get(*b, *a));
+                assert_ne!(table.get(*a, *b), Some(u16::MAX));
+            }
+        }
+
+        assert_eq!(
+            table.get(Pos3D::new(0, 0, 0), Pos3D::new(2, 3, 4)),
+            Some(2 + 3 + 4)
+        );
+        // Opposite face centres are joined by a walk over a side face, never through the inside.
+        assert_eq!(
+            table.get(Pos3D::new(1, 1, 0), Pos3D::new(1, 1, 4)),
+            Some(1 + 4 + 1)
+        );
+        assert_eq!(table.get(Pos3D::new(1, 1, 1), Pos3D::new(0, 0, 0)), None);
+        assert_eq!(table.diameter(), 2 + 3 + 4);
+    }
+
+    #[test]
+    fn walks_go_around_blocked_cells() {
+        let size = Size3D::new(3, 3, 3);
+        // A ring round the middle of the z- face, with its middle fenced in.
+        let blocked: HashSet<Pos3D> = [(0, 1), (1, 0), (1, 2), (2, 1)]
+            .into_iter()
+            .map(|(x, y)| Pos3D::new(x, y, 0))
+            .collect();
+        let table = DistanceTable::with_blocked(size, blocked);
+        let middle = Pos3D::new(1, 1, 0);
+        assert_eq!(table.get(middle, middle), Some(0));
+        assert_eq!(table.get(middle, Pos3D::new(0, 0, 0)), None);
+        assert_eq!(table.get(Pos3D::new(0, 1, 0), Pos3D::new(0, 0, 0)), None);
+        // Corner to corner of the face now climbs over a side.
+        assert_eq!(table.get(Pos3D::new(0, 0, 0), Pos3D::new(2, 0, 0)), Some(4));
+    }
+}
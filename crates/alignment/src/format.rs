@@ -0,0 +1,380 @@
+use derive_new::new;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+use crate::board::{Board, Move};
+use crate::goal::GoalShape;
+use crate::model::{generate_surfaces, Cube, Direction3D, Pos3D, Size3D};
+
+const HEADER: &str = "slide 1";
+
+/// A game in progress, written as text with one keyword and its values per line:
+///
+/// ```text
+/// slide 1
+/// size 3x4x5
+/// seed 42
+/// hole 2,3,4
+/// cube 2,2,4 2,3,4
+/// time 12.5
+/// move 2,2,4 y+ 3.25
+/// ```
+///
+/// The board is the one the game started from. `hole` marks an empty cell, `cube` gives a cube
+/// away from home by its cell and then its home, and every other cell holds its own cube. The
+/// moves lead from there to the board being played now, each with the second it was made at; a
+/// move shifting a line of cubes gives their number after the direction, as in `2,2,4 y+*3`. A
+/// bare puzzle is the same text without the seed, time and moves. A game solved toward anything
+/// but every cube at home names its goal on a line such as `goal mirror-x`, and a board with
+/// obstacles lists them as `wall` cells, which hold no cube, and `pin` cells, whose cube stays.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Game {
+    pub start: Board,
+    /// Seed of the random numbers the board was made with.
+    pub seed: u64,
+    /// What the board is to be solved toward.
+    pub goal: GoalShape,
+    pub moves: Vec<TimedMove>,
+    /// Seconds spent playing so far.
+    pub seconds: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, new)]
+pub struct TimedMove {
+    pub mv: Move,
+    /// Seconds into the game.
+    pub at: f64,
+}
+
+impl Game {
+    pub fn new(start: Board, seed: u64) -> Self {
+        Game {
+            start,
+            seed,
+            goal: GoalShape::Identity,
+            moves: vec![],
+            seconds: 0.0,
+        }
+    }
+
+    /// The board after every move.
+    pub fn current(&self) -> Board {
+        let mut board = self.start.clone();
+        self.moves.iter().for_each(|timed| {
+            board.apply(timed.mv);
+        });
+        board
+    }
+
+    pub fn to_text(&self) -> String {
+        let size = self.start.size();
+        let mut holes = self.start.holes();
+        holes.sort_by_key(|pos| key(*pos));
+        let mut cubes: Vec<(Pos3D, Pos3D)> = self
+            .start
+            .parts()
+            .iter()
+            .filter(|(pos, cube)| **pos != cube.home())
+            .map(|(pos, cube)| (*pos, cube.home()))
+            .collect();
+        cubes.sort_by_key(|(pos, _)| key(*pos));
+
+        let mut text = String::new();
+        // Writing into a String never fails.
+        let _ = writeln!(text, "{}", HEADER);
+        let _ = writeln!(text, "size {}x{}x{}", size.x(), size.y(), size.z());
+        let _ = writeln!(text, "seed {}", self.seed);
+        let mut walls: Vec<Pos3D> = self.start.walls().iter().copied().collect();
+        walls.sort_by_key(|pos| key(*pos));
+        let mut pins: Vec<Pos3D> = self.start.pins().iter().copied().collect();
+        pins.sort_by_key(|pos| key(*pos));
+        if self.goal != GoalShape::Identity {
+            let _ = writeln!(text, "goal {}", self.goal.name());
+        }
+        holes.into_iter().for_each(|pos| {
+            let _ = writeln!(text, "hole {}", pos_text(pos));
+        });
+        walls.into_iter().for_each(|pos| {
+            let _ = writeln!(text, "wall {}", pos_text(pos));
+        });
+        pins.into_iter().for_each(|pos| {
+            let _ = writeln!(text, "pin {}", pos_text(pos));
+        });
+        cubes.into_iter().for_each(|(pos, home)| {
+            let _ = writeln!(text, "cube {} {}", pos_text(pos), pos_text(home));
+        });
+        let _ = writeln!(text, "time {}", self.seconds);
+        self.moves.iter().for_each(|timed| {
+            let _ = writeln!(text, "move {} {}", timed.mv.notation(), timed.at);
+        });
+        text
+    }
+
+    /// The text on a single line of characters that pass through a URL query unescaped, to paste
+    /// or link to.
+    pub fn to_code(&self) -> String {
+        self.to_text()
+            .trim_end()
+            .replace('\n', "/")
+            .replace(' ', "_")
+            .replace('+', "~")
+    }
+
+    pub fn from_code(code: &str) -> Option<Game> {
+        let text = code
+            .trim()
+            .replace('/', "\n")
+            .replace('_', " ")
+            .replace('~', "+");
+        Game::parse(&text)
+    }
+
+    /// Reads a game back, or nothing if a line is malformed or the board or a move is impossible.
+    ///
+    /// Blank lines and lines starting with `#` are skipped.
+    pub fn parse(text: &str) -> Option<Game> {
+        let mut lines = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'));
+        if lines.next()? != HEADER {
+            return None;
+        }
+
+        let mut size = None;
+        let mut seed = 0;
+        let mut goal = GoalShape::Identity;
+        let mut holes = vec![];
+        let mut walls = HashSet::new();
+        let mut pins = HashSet::new();
+        let mut cubes = vec![];
+        let mut seconds = 0.0;
+        let mut moves = vec![];
+        for line in lines {
+            let (key, value) = line.split_once(' ')?;
+            let value = value.trim();
+            match key {
+                "size" => size = Some(parse_size(value)?),
+                "seed" => seed = value.parse().ok()?,
+                "goal" => goal = GoalShape::parse(value)?,
+                "hole" => holes.push(parse_pos(value)?),
+                "wall" => {
+                    walls.insert(parse_pos(value)?);
+                }
+                "pin" => {
+                    pins.insert(parse_pos(value)?);
+                }
+                "cube" => {
+                    let (pos, home) = value.split_once(' ')?;
+                    cubes.push((parse_pos(pos)?, parse_pos(home.trim())?));
+                }
+                "time" => seconds = parse_seconds(value)?,
+                "move" => {
+                    // A move without a time happened together with the one before.
+                    let (mv, at) = match value.rsplit_once(' ') {
+                        Some((mv, at)) if parse_seconds(at).is_some() => (mv, parse_seconds(at)?),
+                        _ => (value, moves.last().map_or(0.0, |prev: &TimedMove| prev.at)),
+                    };
+                    moves.push(TimedMove::new(Move::parse(mv)?, at));
+                }
+                _ => return None,
+            }
+        }
+
+        let size = size?;
+        goal.goal(size)?;
+        let start = board(size, &holes, &walls, &cubes)?.with_obstacles(walls, pins)?;
+        let mut board = start.clone();
+        let playable = moves.iter().all(|timed| board.apply(timed.mv));
+        playable.then_some(Game {
+            start,
+            seed,
+            goal,
+            moves,
+            seconds,
+        })
+    }
+}
+
+impl Move {
+    /// The cell and direction, such as `2,2,4 y+`, followed by the number of cubes for a move of
+    /// more than one, as in `2,2,4 y+*3`.
+    pub fn notation(self) -> String {
+        let count = match self.cubes {
+            1 => String::new(),
+            cubes => format!("*{}", cubes),
+        };
+        format!(
+            "{} {}{}",
+            pos_text(self.src),
+            self.direction.symbol(),
+            count
+        )
+    }
+
+    pub fn parse(value: &str) -> Option<Move> {
+        let (src, rest) = value.trim().split_once(' ')?;
+        let (direction, cubes) = match rest.trim().split_once('*') {
+            Some((direction, cubes)) => (direction, cubes.parse().ok().filter(|n| *n > 0)?),
+            None => (rest.trim(), 1),
+        };
+        Some(Move::line(
+            parse_pos(src)?,
+            Direction3D::parse(direction)?,
+            cubes,
+        ))
+    }
+}
+
+/// Every hole and displaced cube must be on the surface and off the walls, and no home may be
+/// used twice.
+fn board(
+    size: Size3D,
+    holes: &[Pos3D],
+    walls: &HashSet<Pos3D>,
+    cubes: &[(Pos3D, Pos3D)],
+) -> Option<Board> {
+    let surfaces: HashSet<Pos3D> = generate_surfaces(size).into_iter().collect();
+    let mut parts: HashMap<Pos3D, Cube> =
+        surfaces.iter().map(|pos| (*pos, Cube::new(*pos))).collect();
+    for hole in holes.iter().chain(walls) {
+        parts.remove(hole)?;
+    }
+    let mut placed = HashSet::new();
+    for (pos, home) in cubes {
+        if !surfaces.contains(home) || !placed.insert(*pos) {
+            return None;
+        }
+        *parts.get_mut(pos)? = Cube::new(*home);
+    }
+    let homes: HashSet<Pos3D> = parts.values().map(|cube| cube.home()).collect();
+    (homes.len() == parts.len()).then(|| Board::new(size, parts))
+}
+
+fn key(pos: Pos3D) -> (u8, u8, u8) {
+    (pos.x(), pos.y(), pos.z())
+}
+
+fn pos_text(pos: Pos3D) -> String {
+    format!("{},{},{}", pos.x(), pos.y(), pos.z())
+}
+
+fn parse_triple(value: &str, separator: char) -> Option<(u8, u8, u8)> {
+    let vs: Vec<u8> = value
+        .split(separator)
+        .map(|v| v.trim().parse().ok())
+        .collect::<Option<_>>()?;
+    match vs[..] {
+        [x, y, z] => Some((x, y, z)),
+        _ => None,
+    }
+}
+
+fn parse_pos(value: &str) -> Option<Pos3D> {
+    parse_triple(value, ',').map(|(x, y, z)| Pos3D::new(x, y, z))
+}
+
+fn parse_seconds(value: &str) -> Option<f64> {
+    value
+        .parse()
+        .ok()
+        .filter(|v: &f64| v.is_finite() && *v >= 0.0)
+}
+
+fn parse_size(value: &str) -> Option<Size3D> {
+    parse_triple(value, 'x')
+        .filter(|(x, y, z)| [x, y, z].iter().all(|v| **v >= 2))
+        .map(|(x, y, z)| Size3D::new(x, y, z))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::goal::Axis;
+
+    #[test]
+    fn games_read_back() {
+        let size = Size3D::new(3, 4, 5);
+        let hole = Pos3D::new(2, 3, 4);
+        let mut start = Board::with_hole(size, hole);
+        start.slide(Pos3D::new(2, 2, 4), Direction3D::YPosi);
+
+        let mut game = Game::new(start, 42);
+        game.moves = vec![
+            TimedMove::new(Move::new(Pos3D::new(2, 1, 4), Direction3D::YPosi), 1.5),
+            TimedMove::new(Move::new(Pos3D::new(1, 1, 4), Direction3D::XPosi), 3.125),
+        ];
+        game.seconds = 12.25;
+
+        let text = game.to_text();
+        assert!(text.contains("hole 2,2,4\n"));
+        assert!(text.contains("cube 2,3,4 2,2,4\n"));
+        assert!(text.contains("move 2,1,4 y+ 1.5\n"));
+        assert_eq!(Game::parse(&text), Some(game.clone()));
+        game.moves.push(TimedMove::new(
+            Move::line(Pos3D::new(1, 3, 4), Direction3D::YNega, 2),
+            4.0,
+        ));
+        assert!(game.to_text().contains("move 1,3,4 y-*2 4\n"));
+        assert_eq!(Game::parse(&game.to_text()), Some(game.clone()));
+        assert_eq!(Game::from_code(&game.to_code()), Some(game.clone()));
+        game.moves.pop();
+        let code = game.to_code();
+        assert!(!code.contains([' ', '\n', '+']));
+        assert_eq!(Game::from_code(&code), Some(game.clone()));
+        assert_eq!(game.current().holes(), vec![Pos3D::new(1, 1, 4)]);
+        assert!(!text.contains("goal"));
+        game.goal = GoalShape::Checker(Axis::Z);
+        let text = game.to_text();
+        assert!(text.contains("goal checker-z\n"));
+        assert_eq!(Game::parse(&text), Some(game.clone()));
+
+        let walls = [Pos3D::new(0, 0, 0)].into_iter().collect();
+        let pins = [Pos3D::new(0, 1, 0), Pos3D::new(0, 0, 1)]
+            .into_iter()
+            .collect();
+        game.start = game.start.with_obstacles(walls, pins).unwrap();
+        let text = game.to_text();
+        assert!(text.contains("wall 0,0,0\npin 0,0,1\npin 0,1,0\n"));
+        assert_eq!(Game::parse(&text), Some(game.clone()));
+
+        let puzzle =
+            "# two cubes swapped\nslide 1\nsize 2x2x2\ncube 0,0,0 1,0,0\ncube 1,0,0 0,0,0\n";
+        let parsed = Game::parse(puzzle).unwrap();
+        assert_eq!(parsed.start.holes(), vec![]);
+        assert_eq!(parsed.start.at_home(), 6);
+        assert_eq!(parsed.seed, 0);
+    }
+
+    #[test]
+    fn impossible_games_are_refused() {
+        let game = |body: &str| Game::parse(&format!("slide 1\nsize 3x4x5\n{}", body));
+        assert!(game("").is_some());
+        assert!(game("hole 1,1,1\n").is_none());
+        assert!(game("hole 0,0,0\nhole 0,0,0\n").is_none());
+        assert!(game("cube 0,0,0 1,0,0\n").is_none());
+        assert!(game("hole 0,0,0\ncube 0,0,0 1,0,0\n").is_none());
+        assert!(game("hole 0,0,0\nmove 2,2,2 x-\n").is_none());
+        assert!(game("hole 0,0,0\nmove 1,0,0 x-\n").is_some());
+        assert!(game("hole 0,0,0\nmove 1,0,0 x- 2\nmove 0,0,0 x+\n").is_some());
+        assert!(game("hole 0,0,0\nmove 1,0,0 x- soon\n").is_none());
+        assert!(game("hole 0,0,0\nmove 1,0,0 x+\n").is_none());
+        assert!(game("hole 0,0,0\nmove 2,0,0 x-*2 1.5\n").is_some());
+        assert!(game("hole 0,0,0\nmove 2,0,0 x-*2\n").is_some());
+        assert!(game("hole 0,0,0\nmove 2,0,0 x-*1\n").is_none());
+        assert!(game("hole 0,0,0\nmove 2,0,0 x-*0\n").is_none());
+        assert!(game("time -1\n").is_none());
+        assert!(game("color red\n").is_none());
+        assert!(game("goal mirror-y\n").is_some());
+        assert!(game("goal rotate-z\n").is_none());
+        assert!(game("goal upside-down\n").is_none());
+        assert!(game("wall 0,0,0\npin 1,0,0\n").is_some());
+        assert!(game("wall 0,0,0\nhole 0,0,0\n").is_none());
+        assert!(game("wall 0,0,0\ncube 0,0,0 1,0,0\n").is_none());
+        assert!(game("hole 0,0,0\npin 0,0,0\n").is_none());
+        assert!(game("hole 0,0,0\npin 1,0,0\nmove 1,0,0 x-\n").is_none());
+        assert!(game("wall 0,0,0\nhole 2,0,0\nmove 1,0,0 x-\n").is_none());
+        assert!(Game::parse("size 3x4x5\n").is_none());
+        assert!(Game::parse("slide 1\nsize 1x4x5\n").is_none());
+    }
+}
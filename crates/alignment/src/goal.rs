@@ -0,0 +1,534 @@
+use getset::*;
+use std::collections::{HashMap, HashSet};
+
+use crate::board::Board;
+use crate::distance::DistanceTable;
+use crate::model::{generate_surfaces, Cube, Pos3D, Size3D};
+
+/// What one cell must hold for the puzzle to be solved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Requirement {
+    /// The cube from this home.
+    Cube(Pos3D),
+    /// Any cube of this class.
+    Class(u8),
+    /// Any cube at all.
+    Any,
+}
+
+/// The board a puzzle is solved at, as a requirement on every surface cell.
+///
+/// Only cubes are held to it: an empty cell always counts as solved, so the holes end up wherever
+/// the cubes leave them.
+#[derive(Debug, Clone, PartialEq, Eq, Getters, CopyGetters)]
+pub struct Goal {
+    #[getset(get_copy = "pub")]
+    size: Size3D,
+    #[getset(get = "pub")]
+    cells: HashMap<Pos3D, Requirement>,
+    /// The class of every cube that has one, by its home.
+    #[getset(get = "pub")]
+    classes: HashMap<Pos3D, u8>,
+}
+
+impl Goal {
+    /// Requires `cells` of a board of `size`, or nothing if no full board could meet them: a cell
+    /// is missing, a cube is required twice, or a class is short of cubes.
+    pub fn new(
+        size: Size3D,
+        cells: HashMap<Pos3D, Requirement>,
+        classes: HashMap<Pos3D, u8>,
+    ) -> Option<Goal> {
+        let surfaces: HashSet<Pos3D> = generate_surfaces(size).into_iter().collect();
+        if cells.len() != surfaces.len() || !cells.keys().all(|pos| surfaces.contains(pos)) {
+            return None;
+        }
+        let mut required = HashSet::new();
+        let mut wanted: HashMap<u8, usize> = HashMap::new();
+        for requirement in cells.values() {
+            match requirement {
+                Requirement::Cube(home) => {
+                    if !surfaces.contains(home) || !required.insert(*home) {
+                        return None;
+                    }
+                }
+                Requirement::Class(class) => *wanted.entry(*class).or_default() += 1,
+                Requirement::Any => {}
+            }
+        }
+        let mut free: HashMap<u8, usize> = HashMap::new();
+        classes
+            .iter()
+            .filter(|(home, _)| !required.contains(*home))
+            .for_each(|(_, class)| *free.entry(*class).or_default() += 1);
+        let enough = wanted
+            .iter()
+            .all(|(class, count)| free.get(class).copied().unwrap_or_default() >= *count);
+        enough.then_some(Goal {
+            size,
+            cells,
+            classes,
+        })
+    }
+
+    /// Every cube back on its home cell.
+    pub fn identity(size: Size3D) -> Goal {
+        Goal {
+            size,
+            cells: generate_surfaces(size)
+                .into_iter()
+                .map(|pos| (pos, Requirement::Cube(pos)))
+                .collect(),
+            classes: HashMap::new(),
+        }
+    }
+
+    /// Every cell holding any cube from the same faces, as the cubes of one colour are alike on the
+    /// toy: a face's middle takes any middle cube of that face, and an edge or corner any cube
+    /// from the same two or three faces.
+    pub fn faces(size: Size3D) -> Goal {
+        let surfaces = generate_surfaces(size);
+        let classes: HashMap<Pos3D, u8> = surfaces
+            .iter()
+            .map(|pos| (*pos, face_class(*pos, size)))
+            .collect();
+        Goal {
+            size,
+            cells: classes
+                .iter()
+                .map(|(pos, class)| (*pos, Requirement::Class(*class)))
+                .collect(),
+            classes,
+        }
+    }
+
+    /// Each cell holding the cube from `home_of(cell)`, which must map the surface onto itself.
+    pub fn mapped<F>(size: Size3D, home_of: F) -> Option<Goal>
+    where
+        F: Fn(Pos3D) -> Pos3D,
+    {
+        let cells = generate_surfaces(size)
+            .into_iter()
+            .map(|pos| (pos, Requirement::Cube(home_of(pos))))
+            .collect();
+        Goal::new(size, cells, HashMap::new())
+    }
+
+    pub fn requirement(&self, cell: Pos3D) -> Option<Requirement> {
+        self.cells.get(&cell).copied()
+    }
+
+    pub fn accepts(&self, cell: Pos3D, cube: Cube) -> bool {
+        match self.requirement(cell) {
+            Some(Requirement::Cube(home)) => home == cube.home(),
+            Some(Requirement::Class(class)) => self.classes.get(&cube.home()) == Some(&class),
+            Some(Requirement::Any) => true,
+            None => false,
+        }
+    }
+
+    /// Cells `cube` may end up on.
+    pub fn targets(&self, cube: Cube) -> Vec<Pos3D> {
+        self.cells
+            .keys()
+            .copied()
+            .filter(|cell| self.accepts(*cell, cube))
+            .collect()
+    }
+
+    /// Slides from `pos` to the nearest cell `cube` may end up on.
+    pub fn distance(&self, table: &DistanceTable, pos: Pos3D, cube: Cube) -> Option<u16> {
+        self.targets(cube)
+            .into_iter()
+            .filter_map(|cell| table.get(pos, cell))
+            .min()
+    }
+
+    pub fn is_met(&self, board: &Board) -> bool {
+        board
+            .parts()
+            .iter()
+            .all(|(pos, cube)| self.accepts(*pos, *cube))
+    }
+
+    /// Number of cubes on a cell that accepts them.
+    pub fn satisfied(&self, board: &Board) -> usize {
+        board
+            .parts()
+            .iter()
+            .filter(|(pos, cube)| self.accepts(**pos, **cube))
+            .count()
+    }
+
+    /// Sum of the slides every cube is away from the nearest cell that accepts it.
+    pub fn disorder(&self, board: &Board, table: &DistanceTable) -> u32 {
+        board
+            .parts()
+            .iter()
+            .filter_map(|(pos, cube)| self.distance(table, *pos, *cube))
+            .map(u32::from)
+            .sum()
+    }
+
+    /// Whether nothing rules out meeting the goal from `board`, with `table` walking around its
+    /// obstacles: every pinned cube is already accepted where it stands, and every other cube can
+    /// reach a cell that accepts it and, if it is not on one, a hole to move through.
+    ///
+    /// The checks see only where each cube could go, not whether the cubes can all get out of each
+    /// other's way at once.
+    pub fn solvable(&self, board: &Board, table: &DistanceTable) -> bool {
+        let holes = board.holes();
+        board.parts().iter().all(|(pos, cube)| {
+            if self.accepts(*pos, *cube) {
+                return true;
+            }
+            !board.pins().contains(pos)
+                && self.distance(table, *pos, *cube).is_some()
+                && holes.iter().any(|hole| table.get(*pos, *hole).is_some())
+        })
+    }
+
+    /// A board meeting the goal with `holes` left empty.
+    pub fn solved(&self, holes: &[Pos3D]) -> Board {
+        let mut cells: Vec<(Pos3D, Requirement)> =
+            self.cells.iter().map(|(pos, r)| (*pos, *r)).collect();
+        cells.sort_by_key(|(pos, _)| (pos.x(), pos.y(), pos.z()));
+        let required: HashSet<Pos3D> = cells
+            .iter()
+            .filter_map(|(_, r)| match r {
+                Requirement::Cube(home) => Some(*home),
+                _ => None,
+            })
+            .collect();
+        let mut free: Vec<Pos3D> = generate_surfaces(self.size)
+            .into_iter()
+            .filter(|home| !required.contains(home))
+            .collect();
+        free.sort_by_key(|pos| (pos.x(), pos.y(), pos.z()));
+
+        let mut take = |class: Option<u8>| {
+            let i = free
+                .iter()
+                .position(|home| class.is_none() || self.classes.get(home) == class.as_ref())?;
+            Some(free.remove(i))
+        };
+        // Classes first, so the cells taking any cube cannot use up a class.
+        let mut parts = HashMap::new();
+        for (pos, requirement) in cells.iter() {
+            let home = match requirement {
+                Requirement::Cube(home) => Some(*home),
+                Requirement::Class(class) => take(Some(*class)),
+                Requirement::Any => continue,
+            };
+            if let Some(home) = home {
+                parts.insert(*pos, Cube::new(home));
+            }
+        }
+        for (pos, requirement) in cells.iter() {
+            if *requirement == Requirement::Any {
+                if let Some(home) = take(None) {
+                    parts.insert(*pos, Cube::new(home));
+                }
+            }
+        }
+        holes.iter().for_each(|hole| {
+            parts.remove(hole);
+        });
+        Board::new(self.size, parts)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+impl Axis {
+    pub const ALL: [Axis; 3] = [Axis::X, Axis::Y, Axis::Z];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Axis::X => "x",
+            Axis::Y => "y",
+            Axis::Z => "z",
+        }
+    }
+}
+
+/// Goals a designer can ask for by name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum GoalShape {
+    #[default]
+    Identity,
+    /// Cubes from the same faces taken as alike.
+    Faces,
+    /// The box mirrored along the axis, swapping its two faces across it.
+    Mirror(Axis),
+    /// The picture on the positive face of the axis turned a quarter, which needs a square face.
+    Rotate(Axis),
+    /// The middles of both faces across the axis mixed into one checkerboard of their cubes.
+    Checker(Axis),
+}
+
+impl GoalShape {
+    pub const ALL: [GoalShape; 11] = [
+        GoalShape::Identity,
+        GoalShape::Faces,
+        GoalShape::Mirror(Axis::X),
+        GoalShape::Mirror(Axis::Y),
+        GoalShape::Mirror(Axis::Z),
+        GoalShape::Rotate(Axis::X),
+        GoalShape::Rotate(Axis::Y),
+        GoalShape::Rotate(Axis::Z),
+        GoalShape::Checker(Axis::X),
+        GoalShape::Checker(Axis::Y),
+        GoalShape::Checker(Axis::Z),
+    ];
+
+    pub fn name(self) -> String {
+        match self {
+            GoalShape::Identity => "identity".to_string(),
+            GoalShape::Faces => "faces".to_string(),
+            GoalShape::Mirror(axis) => format!("mirror-{}", axis.name()),
+            GoalShape::Rotate(axis) => format!("rotate-{}", axis.name()),
+            GoalShape::Checker(axis) => format!("checker-{}", axis.name()),
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        let name = name.trim().to_lowercase();
+        GoalShape::ALL
+            .into_iter()
+            .find(|shape| shape.name() == name)
+    }
+
+    #[must_use]
+    pub fn next(self) -> Self {
+        let i = GoalShape::ALL.iter().position(|s| *s == self).unwrap_or(0);
+        GoalShape::ALL[(i + 1) % GoalShape::ALL.len()]
+    }
+
+    #[must_use]
+    pub fn prev(self) -> Self {
+        let i = GoalShape::ALL.iter().position(|s| *s == self).unwrap_or(0);
+        GoalShape::ALL[(i + GoalShape::ALL.len() - 1) % GoalShape::ALL.len()]
+    }
+
+    /// The goal on a box of `size`, or nothing if the shape does not fit it.
+    pub fn goal(self, size: Size3D) -> Option<Goal> {
+        match self {
+            GoalShape::Identity => Some(Goal::identity(size)),
+            GoalShape::Faces => Some(Goal::faces(size)),
+            GoalShape::Mirror(axis) => Goal::mapped(size, |pos| {
+                let (a, b, c, n) = split(pos, size, axis);
+                join(n - 1 - a, b, c, axis)
+            }),
+            GoalShape::Rotate(axis) => {
+                let (_, _, _, n) = split(Pos3D::default(), size, axis);
+                let (u, v) = face_dims(size, axis);
+                if u != v {
+                    return None;
+                }
+                Goal::mapped(size, |pos| {
+                    let (a, b, c, _) = split(pos, size, axis);
+                    if a == n - 1 {
+                        join(a, c, u - 1 - b, axis)
+                    } else {
+                        pos
+                    }
+                })
+            }
+            GoalShape::Checker(axis) => {
+                let (u, v) = face_dims(size, axis);
+                let inner = |pos: Pos3D| {
+                    let (a, b, c, n) = split(pos, size, axis);
+                    let middle = 0 < b && b < u - 1 && 0 < c && c < v - 1;
+                    // Class 0 for the negative face, 1 for the positive one.
+                    (middle && (a == 0 || a == n - 1)).then_some((u8::from(a != 0), (b + c) % 2))
+                };
+                let surfaces = generate_surfaces(size);
+                let classes = surfaces
+                    .iter()
+                    .filter_map(|pos| inner(*pos).map(|(side, _)| (*pos, side)))
+                    .collect();
+                let cells = surfaces
+                    .iter()
+                    .map(|pos| {
+                        let requirement = match inner(*pos) {
+                            Some((side, parity)) => Requirement::Class(side ^ parity),
+                            None => Requirement::Cube(*pos),
+                        };
+                        (*pos, requirement)
+                    })
+                    .collect();
+                Goal::new(size, cells, classes)
+            }
+        }
+    }
+}
+
+/// One bit for every face `pos` lies on, in `Direction3D` order.
+fn face_class(pos: Pos3D, size: Size3D) -> u8 {
+    pos.get_faces(size)
+        .into_iter()
+        .fold(0, |class, d| class | 1 << d as u8)
+}
+
+/// The coordinate along `axis`, the other two in order, and the length of the axis.
+pub(crate) fn split(pos: Pos3D, size: Size3D, axis: Axis) -> (u8, u8, u8, u8) {
+    match axis {
+        Axis::X => (pos.x(), pos.y(), pos.z(), size.x()),
+        Axis::Y => (pos.y(), pos.z(), pos.x(), size.y()),
+        Axis::Z => (pos.z(), pos.x(), pos.y(), size.z()),
+    }
+}
+
+pub(crate) fn join(a: u8, b: u8, c: u8, axis: Axis) -> Pos3D {
+    match axis {
+        Axis::X => Pos3D::new(a, b, c),
+        Axis::Y => Pos3D::new(c, a, b),
+        Axis::Z => Pos3D::new(b, c, a),
+    }
+}
+
+/// Lengths of a face across `axis`, in the order `split` gives its coordinates.
+pub(crate) fn face_dims(size: Size3D, axis: Axis) -> (u8, u8) {
+    match axis {
+        Axis::X => (size.y(), size.z()),
+        Axis::Y => (size.z(), size.x()),
+        Axis::Z => (size.x(), size.y()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::board::Move;
+    use crate::model::Direction3D;
+
+    #[test]
+    fn identity_matches_homes() {
+        let size = Size3D::new(3, 4, 5);
+        let goal = Goal::identity(size);
+        let hole = Pos3D::new(2, 3, 4);
+        let mut board = goal.solved(&[hole]);
+        assert_eq!(board, Board::with_hole(size, hole));
+        assert!(goal.is_met(&board));
+
+        assert!(board.apply(Move::new(Pos3D::new(2, 2, 4), Direction3D::YPosi)));
+        assert!(!goal.is_met(&board));
+        assert_eq!(goal.satisfied(&board), board.at_home());
+        let table = DistanceTable::new(size);
+        assert_eq!(goal.disorder(&board, &table), board.disorder(&table));
+    }
+
+    #[test]
+    fn shapes_make_reachable_goals() {
+        let size = Size3D::new(3, 4, 4);
+        let table = DistanceTable::new(size);
+        GoalShape::ALL.into_iter().for_each(|shape| {
+            assert_eq!(GoalShape::parse(&shape.name()), Some(shape));
+            assert_eq!(shape.next().prev(), shape);
+            let goal = match shape.goal(size) {
+                Some(goal) => goal,
+                // Only the square face across x turns.
+                None => {
+                    assert!(matches!(shape, GoalShape::Rotate(Axis::Y | Axis::Z)));
+                    return;
+                }
+            };
+            let hole = Pos3D::new(0, 0, 0);
+            let board = goal.solved(&[hole]);
+            assert_eq!(board.parts().len(), generate_surfaces(size).len() - 1);
+            assert!(goal.is_met(&board), "{:?}", shape);
+            assert_eq!(goal.disorder(&board, &table), 0);
+            // Alike cubes are put on their own homes first.
+            if !matches!(shape, GoalShape::Identity | GoalShape::Faces) {
+                assert!(!board.is_solved(), "{:?}", shape);
+            }
+        });
+
+        let checker = GoalShape::Checker(Axis::Y).goal(size).unwrap();
+        assert_eq!(checker.classes().len(), 4);
+        assert!(checker
+            .cells()
+            .values()
+            .any(|r| *r == Requirement::Class(0)));
+        let rotated = GoalShape::Rotate(Axis::X).goal(size).unwrap();
+        assert_eq!(
+            rotated.requirement(Pos3D::new(2, 0, 0)),
+            Some(Requirement::Cube(Pos3D::new(2, 0, 3)))
+        );
+    }
+
+    #[test]
+    fn faces_take_alike_cubes_as_one() {
+        let size = Size3D::new(3, 4, 5);
+        let goal = Goal::faces(size);
+        let distinct: HashSet<u8> = goal.classes().values().copied().collect();
+        // Six faces, twelve edges and eight corners.
+        assert_eq!(distinct.len(), 26);
+
+        let (a, b) = (Pos3D::new(1, 1, 0), Pos3D::new(1, 2, 0));
+        let mut parts = Board::solved(size).parts().clone();
+        parts.insert(a, Cube::new(b));
+        parts.insert(b, Cube::new(a));
+        let swapped = Board::new(size, parts.clone());
+        assert!(goal.is_met(&swapped));
+        assert!(!swapped.is_solved());
+
+        // An edge cube is no corner.
+        let (edge, corner) = (Pos3D::new(1, 0, 0), Pos3D::new(0, 0, 0));
+        parts.insert(edge, Cube::new(corner));
+        parts.insert(corner, Cube::new(edge));
+        let swapped = Board::new(size, parts);
+        assert!(!goal.is_met(&swapped));
+        let table = DistanceTable::new(size);
+        assert_eq!(goal.disorder(&swapped, &table), 2);
+    }
+
+    #[test]
+    fn obstacles_can_make_goals_unreachable() {
+        let size = Size3D::new(3, 3, 3);
+        let goal = Goal::identity(size);
+        let ring: HashSet<Pos3D> = [(0, 1), (1, 0), (1, 2), (2, 1)]
+            .into_iter()
+            .map(|(x, y)| Pos3D::new(x, y, 0))
+            .collect();
+        let board = Board::with_hole(size, Pos3D::new(0, 0, 2))
+            .with_obstacles(ring.clone(), HashSet::new())
+            .unwrap();
+        let table = DistanceTable::with_blocked(size, board.blocked());
+        assert!(goal.solvable(&board, &table));
+
+        // The fenced in middle cannot get home once it has been swapped out.
+        let mut parts = board.parts().clone();
+        let (middle, corner) = (Pos3D::new(1, 1, 0), Pos3D::new(0, 0, 0));
+        parts.insert(middle, Cube::new(corner));
+        parts.insert(corner, Cube::new(middle));
+        let swapped = Board::new(size, parts)
+            .with_obstacles(ring, HashSet::new())
+            .unwrap();
+        assert!(!goal.solvable(&swapped, &table));
+
+        let pinned = Board::new(size, swapped.parts().clone())
+            .with_obstacles(HashSet::new(), [corner].into_iter().collect())
+            .unwrap();
+        let table = DistanceTable::with_blocked(size, pinned.blocked());
+        assert!(!goal.solvable(&pinned, &table));
+    }
+
+    #[test]
+    fn impossible_goals_are_refused() {
+        let size = Size3D::new(2, 2, 2);
+        let corner = Pos3D::new(0, 0, 0);
+        assert!(Goal::mapped(size, |_| corner).is_none());
+        let mut cells = Goal::identity(size).cells().clone();
+        cells.insert(corner, Requirement::Class(3));
+        assert!(Goal::new(size, cells.clone(), HashMap::new()).is_none());
+        let classes = [(corner, 3)].into_iter().collect();
+        assert!(Goal::new(size, cells, classes).is_some());
+    }
+}
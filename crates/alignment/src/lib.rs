@@ -1,2 +1,8 @@
+pub mod belt;
+pub mod board;
+pub mod distance;
+pub mod format;
+pub mod goal;
 pub mod model;
 pub mod slide;
+pub mod solver;
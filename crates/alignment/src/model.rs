@@ -1,6 +1,7 @@
 use derive_new::new;
 use getset::*;
 use std::{hash::Hash, vec};
+use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 use tinyvec::*;
 
@@ -14,8 +15,9 @@ pub struct Size3D {
     z: u8,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, EnumIter)]
 pub enum Direction3D {
+    #[default]
     XNega,
     XPosi,
     YNega,
@@ -24,12 +26,6 @@ pub enum Direction3D {
     ZPosi,
 }
 
-impl Default for Direction3D {
-    fn default() -> Self {
-        Direction3D::XNega
-    }
-}
-
 impl Direction3D {
     #[must_use]
     pub fn invert(self) -> Self {
@@ -42,6 +38,22 @@ impl Direction3D {
             Direction3D::ZPosi => Direction3D::ZNega,
         }
     }
+
+    /// Short name of the axis and its sign, such as `x-` or `z+`.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Direction3D::XNega => "x-",
+            Direction3D::XPosi => "x+",
+            Direction3D::YNega => "y-",
+            Direction3D::YPosi => "y+",
+            Direction3D::ZNega => "z-",
+            Direction3D::ZPosi => "z+",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Direction3D::iter().find(|d| d.symbol() == value)
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, new, CopyGetters)]
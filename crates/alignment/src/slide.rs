@@ -2,7 +2,7 @@ use std::collections::HashMap;
 use strum::IntoEnumIterator;
 use tinyvec::*;
 
-use crate::model::{Cube, Direction3D, Pos3D, Size3D};
+use crate::model::{generate_surfaces, Cube, Direction3D, Pos3D, Size3D};
 
 pub fn move_one(pos: Pos3D, size: Size3D, d: Direction3D) -> Option<Pos3D> {
     match d {
@@ -40,6 +40,47 @@ pub fn slide(parts: &mut HashMap<Pos3D, Cube>, size: Size3D, src: Pos3D, d: Dire
     }
 }
 
+/// The straight surface line leaving `start` toward `d`: every cell after `start` with the
+/// direction of the step into it.
+///
+/// The line bends over the box edges onto the face ahead, and ends before it would come back
+/// round to `start`. A cell on an edge running along `d` keeps to the first of its faces across
+/// `d` when the line reaches a corner. Empty if `d` leads off the surface.
+pub fn line(start: Pos3D, size: Size3D, d: Direction3D) -> Vec<(Pos3D, Direction3D)> {
+    let mut face = match start
+        .get_faces(size)
+        .into_iter()
+        .find(|face| *face != d && *face != d.invert())
+    {
+        Some(face) => face,
+        None => return vec![],
+    };
+    let limit = generate_surfaces(size).len();
+    let mut results = vec![];
+    let (mut pos, mut d) = (start, d);
+    while results.len() < limit {
+        let next = match move_one(pos, size, d) {
+            Some(next) => next,
+            None => {
+                // Over the edge, heading away from the face left behind.
+                let turned = face.invert();
+                face = d;
+                d = turned;
+                match move_one(pos, size, d) {
+                    Some(next) => next,
+                    None => break,
+                }
+            }
+        };
+        if next == start || !next.on_face(size) {
+            break;
+        }
+        results.push((next, d));
+        pos = next;
+    }
+    results
+}
+
 pub fn adjacents(center: Pos3D, size: Size3D) -> ArrayVec<[Direction3D; 4]> {
     let mut results = array_vec!([Direction3D; 4]);
     Direction3D::iter().for_each(|d| {
@@ -259,6 +300,34 @@ mod test {
         assert!(!slide(&mut parts, size, pos101, Direction3D::ZPosi));
     }
 
+    #[test]
+    fn lines_bend_round_edges() {
+        let size = Size3D::new(3, 4, 5);
+        let from = Pos3D::new(1, 1, 0);
+        let cells: Vec<Pos3D> = line(from, size, Direction3D::XPosi)
+            .into_iter()
+            .map(|(pos, _)| pos)
+            .collect();
+        // Across the z- face, up the x+ face, back over the z+ face and down the x- face.
+        assert_eq!(cells.len(), 2 * (2 + 4) - 1);
+        assert_eq!(
+            &cells[..3],
+            &[
+                Pos3D::new(2, 1, 0),
+                Pos3D::new(2, 1, 1),
+                Pos3D::new(2, 1, 2),
+            ]
+        );
+        assert_eq!(cells[4], Pos3D::new(2, 1, 4));
+        assert_eq!(cells[5], Pos3D::new(1, 1, 4));
+        assert_eq!(cells[10], Pos3D::new(0, 1, 0));
+        assert_eq!(
+            line(from, size, Direction3D::XPosi)[1].1,
+            Direction3D::ZPosi
+        );
+        assert!(line(from, size, Direction3D::ZPosi).is_empty());
+    }
+
     #[test]
     fn invert_direction() {
         let size = Size3D::new(3, 3, 3);
@@ -0,0 +1,564 @@
+use derive_new::new;
+use std::cmp::Reverse;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BinaryHeap, HashMap};
+use std::hash::{Hash, Hasher};
+use strum::IntoEnumIterator;
+
+use crate::board::{Board, Move};
+use crate::distance::DistanceTable;
+use crate::goal::Goal;
+use crate::model::{generate_surfaces, Cube, Direction3D, Pos3D, Size3D};
+use crate::slide::line;
+
+pub(crate) const EMPTY: u16 = u16::MAX;
+
+/// The moves a search settled on, and whether they finish the puzzle.
+///
+/// A plan that finishes the puzzle is also a shortest one.
+#[derive(Debug, Clone, PartialEq, Eq, new)]
+pub struct Plan<M = Move> {
+    pub moves: Vec<M>,
+    pub solved: bool,
+    /// Boards taken off the open list before the search stopped.
+    pub expanded: usize,
+}
+
+/// What a move costs in the plans a search finds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    /// Every slide of one cube is a move.
+    Slide,
+    /// Shifting a whole line of cubes toward the hole is a single move.
+    Line,
+}
+
+/// A move into one hole: the cells along its line from the cube pushed to the hole.
+struct Feeder {
+    mv: Move,
+    chain: Vec<usize>,
+}
+
+/// A* over boards toward a goal.
+///
+/// Under the slide metric the estimate is the sum of every cube's geodesic distance to the nearest
+/// cell that accepts it. A slide moves one cube one cell, so the estimate never overstates the
+/// slides left and changes by at most one with every slide; the first solved board taken off the
+/// open list is reached by the fewest slides. Under the line metric a move shifts any number of
+/// cubes one cell each, so the estimate is the larger of the furthest cube's distance and the sum
+/// shared out over the longest line, which keep both properties for line moves.
+///
+/// Walls and pinned cubes of the board searched from stay out of every move. Built from a table
+/// that walks around them, the estimate stays closer to the moves left.
+///
+/// Cubes the goal accepts on the same cells are interchangeable, so a board keeps the same token
+/// for all of them: boards differing only in which of them sits where are searched once.
+///
+/// Boards on anything but the smallest boxes are far too large to search to the end, so the search
+/// gives up after a budget of expanded boards and returns the way to the most promising one seen.
+pub struct Solver {
+    size: Size3D,
+    tokens: Tokens,
+    metric: Metric,
+    /// For every cell, the moves that can fill it.
+    feeders: Vec<Vec<Feeder>>,
+    /// Most cubes one move shifts.
+    longest: u32,
+}
+
+impl Solver {
+    /// Solves back to every cube at home.
+    pub fn new(table: &DistanceTable) -> Self {
+        Solver::with_goal(table, &Goal::identity(table.size()))
+    }
+
+    pub fn with_goal(table: &DistanceTable, goal: &Goal) -> Self {
+        let mut solver = Solver {
+            size: table.size(),
+            tokens: Tokens::new(table, goal),
+            metric: Metric::Slide,
+            feeders: vec![],
+            longest: 1,
+        };
+        solver.link();
+        solver
+    }
+
+    /// Counts plans in `metric` moves instead of slides.
+    pub fn with_metric(mut self, metric: Metric) -> Self {
+        self.metric = metric;
+        self.link();
+        self
+    }
+
+    /// Collects the moves into every cell the metric allows.
+    fn link(&mut self) {
+        let cells = &self.tokens.cells;
+        let index = &self.tokens.index;
+        let mut feeders: Vec<Vec<Feeder>> = cells.iter().map(|_| vec![]).collect();
+        for (i, src) in cells.iter().enumerate() {
+            for d in Direction3D::iter() {
+                let path = line(*src, self.size, d);
+                if path.first().map(|(_, first)| *first) != Some(d) {
+                    continue;
+                }
+                let count = match self.metric {
+                    Metric::Slide => 1,
+                    Metric::Line => path.len(),
+                };
+                let mut chain = vec![i];
+                for (k, (pos, _)) in path.iter().take(count).enumerate() {
+                    // Further along the line than a move can count, as the board never offers.
+                    let cubes = match u8::try_from(k + 1) {
+                        Ok(cubes) => cubes,
+                        Err(_) => break,
+                    };
+                    chain.push(index[pos]);
+                    feeders[index[pos]].push(Feeder {
+                        mv: Move::line(*src, d, cubes),
+                        chain: chain.clone(),
+                    });
+                }
+            }
+        }
+        self.longest = feeders
+            .iter()
+            .flatten()
+            .map(|feeder| feeder.chain.len() as u32 - 1)
+            .max()
+            .unwrap_or(1);
+        self.feeders = feeders;
+    }
+
+    fn estimate(&self, cells: &[u16], disorder: u32) -> u32 {
+        match self.metric {
+            Metric::Slide => disorder,
+            Metric::Line => self.tokens.estimate(cells, disorder, self.longest),
+        }
+    }
+
+    /// Searches from `board` until it is solved or `budget` boards have been expanded.
+    pub fn solve(&self, board: &Board, budget: usize) -> Plan {
+        let tokens = &self.tokens;
+        let blocked = board.blocked();
+        let free: Vec<bool> = tokens
+            .cells
+            .iter()
+            .map(|pos| !blocked.contains(pos))
+            .collect();
+        let expand = |cells: &[u16], disorder: u32, next: &mut Vec<(Move, Vec<u16>, u32)>| {
+            let holes = (0..cells.len()).filter(|i| cells[*i] == EMPTY && free[*i]);
+            for hole in holes {
+                for feeder in self.feeders[hole].iter() {
+                    let (pushed, hole) = feeder.chain.split_at(feeder.chain.len() - 1);
+                    if !pushed.iter().all(|i| cells[*i] != EMPTY && free[*i]) {
+                        continue;
+                    }
+                    // Every token steps one cell along the chain.
+                    let mut after = cells.to_vec();
+                    let mut disorder = disorder;
+                    for (from, to) in pushed.iter().zip(pushed[1..].iter().chain(hole)) {
+                        let token = cells[*from] as usize;
+                        disorder = disorder + tokens.steps(*to, token) - tokens.steps(*from, token);
+                        after[*to] = token as u16;
+                    }
+                    after[pushed[0]] = EMPTY;
+                    next.push((feeder.mv, after, disorder));
+                }
+            }
+        };
+        let root = tokens.encode(board);
+        let disorder = tokens.disorder(&root);
+        search(
+            root,
+            disorder,
+            budget,
+            |cells, disorder| self.estimate(cells, disorder),
+            expand,
+        )
+    }
+}
+
+/// Cells of a box as a search sees them toward a goal.
+pub(crate) struct Tokens {
+    pub cells: Vec<Pos3D>,
+    pub index: HashMap<Pos3D, usize>,
+    /// For every cube by its home, the first home whose cube the goal takes as alike.
+    tokens: Vec<u16>,
+    /// For every token, the slides from each cell to the nearest cell that accepts it; more than
+    /// any walk on the surface takes when none can be reached.
+    costs: Vec<Vec<u32>>,
+}
+
+impl Tokens {
+    pub fn new(table: &DistanceTable, goal: &Goal) -> Self {
+        let cells = generate_surfaces(table.size());
+        let index = cells.iter().enumerate().map(|(i, pos)| (*pos, i)).collect();
+        let targets: Vec<Vec<Pos3D>> = cells
+            .iter()
+            .map(|home| {
+                let mut targets = goal.targets(Cube::new(*home));
+                targets.sort_by_key(|pos| (pos.x(), pos.y(), pos.z()));
+                targets
+            })
+            .collect();
+        let tokens = targets
+            .iter()
+            .map(|ts| {
+                targets
+                    .iter()
+                    .position(|other| other == ts)
+                    .unwrap_or_default() as u16
+            })
+            .collect();
+        // Never zero, so a board with a cube cut off from every cell that accepts it is never
+        // taken for solved.
+        let unreachable = cells.len() as u32;
+        let costs = targets
+            .iter()
+            .map(|targets| {
+                cells
+                    .iter()
+                    .map(|pos| {
+                        targets
+                            .iter()
+                            .filter_map(|target| table.get(*pos, *target))
+                            .min()
+                            .map_or(unreachable, u32::from)
+                    })
+                    .collect()
+            })
+            .collect();
+        Tokens {
+            cells,
+            index,
+            tokens,
+            costs,
+        }
+    }
+
+    /// The token on every cell of `board`; holes and walls are empty.
+    pub fn encode(&self, board: &Board) -> Vec<u16> {
+        let mut cells = vec![EMPTY; self.cells.len()];
+        board.parts().iter().for_each(|(pos, cube)| {
+            if let (Some(i), Some(home)) = (self.index.get(pos), self.index.get(&cube.home())) {
+                cells[*i] = self.tokens[*home];
+            }
+        });
+        cells
+    }
+
+    pub fn steps(&self, from: usize, token: usize) -> u32 {
+        self.costs[token][from]
+    }
+
+    fn distances<'a>(&'a self, cells: &'a [u16]) -> impl Iterator<Item = u32> + 'a {
+        cells
+            .iter()
+            .enumerate()
+            .filter(|(_, token)| **token != EMPTY)
+            .map(|(i, token)| self.steps(i, *token as usize))
+    }
+
+    /// Sum of every cube's distance to the nearest cell that accepts it.
+    pub fn disorder(&self, cells: &[u16]) -> u32 {
+        self.distances(cells).sum()
+    }
+
+    /// The fewest moves left when one move carries up to `longest` cubes a cell each: no fewer
+    /// than the furthest cube needs, nor than the whole disorder shared out between them.
+    pub fn estimate(&self, cells: &[u16], disorder: u32, longest: u32) -> u32 {
+        let furthest = self.distances(cells).max().unwrap_or_default();
+        furthest.max(disorder.div_ceil(longest.max(1)))
+    }
+}
+
+struct Node<M> {
+    cells: Vec<u16>,
+    parent: usize,
+    mv: Option<M>,
+    steps: u32,
+    /// Sum of every cube's distance to the nearest cell that accepts it.
+    disorder: u32,
+    estimate: u32,
+}
+
+/// A* from the board `root`, `disorder` away from the goal, until it is solved or `budget` boards
+/// have been expanded.
+///
+/// `expand` lists the moves out of a board with the boards and disorders they lead to, and
+/// `estimate` must neither overstate the moves left nor drop by more than one with a move.
+pub(crate) fn search<M: Copy>(
+    root: Vec<u16>,
+    disorder: u32,
+    budget: usize,
+    estimate: impl Fn(&[u16], u32) -> u32,
+    expand: impl Fn(&[u16], u32, &mut Vec<(M, Vec<u16>, u32)>),
+) -> Plan<M> {
+    let root_estimate = estimate(&root, disorder);
+    let mut nodes = vec![Node {
+        cells: root,
+        parent: 0,
+        mv: None,
+        steps: 0,
+        disorder,
+        estimate: root_estimate,
+    }];
+    // The fewest steps each board has been reached by.
+    let mut seen = HashMap::new();
+    seen.insert(fingerprint(&nodes[0].cells), 0);
+    let mut open = BinaryHeap::new();
+    open.push(Reverse((root_estimate, root_estimate, 0)));
+
+    // The best board short of the goal: lowest estimate, then fewest steps, excluding the start
+    // unless nothing else was reached.
+    let mut best = 0;
+    let mut expanded = 0;
+    let mut next = vec![];
+    while let Some(Reverse((_, _, current))) = open.pop() {
+        if seen[&fingerprint(&nodes[current].cells)] < nodes[current].steps {
+            // Reached by a shorter way since it was pushed.
+            continue;
+        }
+        if nodes[current].estimate == 0 {
+            best = current;
+            break;
+        }
+        if expanded >= budget {
+            break;
+        }
+        expanded += 1;
+
+        expand(&nodes[current].cells, nodes[current].disorder, &mut next);
+        for (mv, cells, disorder) in next.drain(..) {
+            let steps = nodes[current].steps + 1;
+            let key = fingerprint(&cells);
+            if seen.get(&key).is_some_and(|prev| *prev <= steps) {
+                continue;
+            }
+            seen.insert(key, steps);
+            let estimate = estimate(&cells, disorder);
+            let id = nodes.len();
+            nodes.push(Node {
+                cells,
+                parent: current,
+                mv: Some(mv),
+                steps,
+                disorder,
+                estimate,
+            });
+            let better = |a: &Node<M>, b: &Node<M>| (a.estimate, a.steps) < (b.estimate, b.steps);
+            if best == 0 || better(&nodes[id], &nodes[best]) {
+                best = id;
+            }
+            open.push(Reverse((steps + estimate, estimate, id)));
+        }
+    }
+
+    let mut moves = vec![];
+    let mut at = best;
+    while let Some(mv) = nodes[at].mv {
+        moves.push(mv);
+        at = nodes[at].parent;
+    }
+    moves.reverse();
+    Plan::new(moves, nodes[best].estimate == 0, expanded)
+}
+
+fn fingerprint(cells: &[u16]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    cells.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::goal::{Axis, GoalShape};
+    use crate::model::Size3D;
+    use std::collections::HashSet;
+
+    #[test]
+    fn solves_short_scrambles() {
+        let size = Size3D::new(3, 3, 3);
+        let table = DistanceTable::new(size);
+        let solver = Solver::new(&table);
+        let hole = Pos3D::new(0, 0, 0);
+
+        let solved = Board::with_hole(size, hole);
+        assert_eq!(solver.solve(&solved, 100), Plan::new(vec![], true, 0));
+
+        let mut board = solved.clone();
+        let scramble = [
+            Move::new(Pos3D::new(1, 0, 0), Direction3D::XNega),
+            Move::new(Pos3D::new(1, 1, 0), Direction3D::YNega),
+            Move::new(Pos3D::new(0, 1, 0), Direction3D::XPosi),
+            Move::new(Pos3D::new(0, 0, 0), Direction3D::YPosi),
+        ];
+        scramble
+            .iter()
+            .for_each(|mv| assert!(board.apply(*mv), "{:?}", mv));
+
+        let plan = solver.solve(&board, 10_000);
+        assert!(plan.solved);
+        assert_eq!(plan.moves.len(), scramble.len());
+        plan.moves
+            .iter()
+            .for_each(|mv| assert!(board.apply(*mv), "{:?}", mv));
+        assert_eq!(board, solved);
+    }
+
+    #[test]
+    fn solves_back_to_other_goals() {
+        let size = Size3D::new(3, 3, 3);
+        let table = DistanceTable::new(size);
+        let goal = GoalShape::Mirror(Axis::X).goal(size).unwrap();
+        let solver = Solver::with_goal(&table, &goal);
+        let solved = goal.solved(&[Pos3D::new(0, 0, 0)]);
+        assert!(!solved.is_solved());
+        assert!(solver.solve(&solved, 100).moves.is_empty());
+
+        let mut board = solved.clone();
+        assert!(board.apply(Move::new(Pos3D::new(0, 1, 0), Direction3D::YNega)));
+        assert!(board.apply(Move::new(Pos3D::new(0, 2, 0), Direction3D::YNega)));
+        assert!(board.apply(Move::new(Pos3D::new(1, 2, 0), Direction3D::XNega)));
+        let plan = solver.solve(&board, 10_000);
+        assert!(plan.solved);
+        assert_eq!(plan.moves.len(), 3);
+        plan.moves.iter().for_each(|mv| assert!(board.apply(*mv)));
+        assert!(goal.is_met(&board));
+        assert_eq!(board, solved);
+    }
+
+    #[test]
+    fn alike_cubes_are_searched_once() {
+        let size = Size3D::new(3, 4, 4);
+        let table = DistanceTable::new(size);
+        let goal = Goal::faces(size);
+        let solver = Solver::with_goal(&table, &goal);
+        let solved = Board::with_hole(size, Pos3D::new(1, 1, 0));
+
+        // One middle cube of a face in the place of another is as good as home.
+        let mut board = solved.clone();
+        assert!(board.apply(Move::new(Pos3D::new(1, 2, 0), Direction3D::YNega)));
+        assert!(solver.solve(&board, 100).moves.is_empty());
+        assert_eq!(Solver::new(&table).solve(&board, 100).moves.len(), 1);
+
+        let scramble = [
+            Move::new(Pos3D::new(2, 2, 0), Direction3D::XNega),
+            Move::new(Pos3D::new(2, 1, 0), Direction3D::YPosi),
+            Move::new(Pos3D::new(1, 1, 0), Direction3D::XPosi),
+            Move::new(Pos3D::new(1, 2, 0), Direction3D::YNega),
+        ];
+        scramble
+            .iter()
+            .for_each(|mv| assert!(board.apply(*mv), "{:?}", mv));
+        assert!(!goal.is_met(&board));
+        let plan = solver.solve(&board, 10_000);
+        assert!(plan.solved);
+        assert!(plan.moves.len() <= scramble.len());
+        plan.moves.iter().for_each(|mv| assert!(board.apply(*mv)));
+        assert!(goal.is_met(&board));
+    }
+
+    #[test]
+    fn slides_around_obstacles() {
+        let size = Size3D::new(3, 3, 3);
+        let hole = Pos3D::new(1, 1, 0);
+        let walls = [Pos3D::new(0, 1, 0)].into_iter().collect();
+        let pins = [Pos3D::new(1, 0, 0)].into_iter().collect();
+        let solved = Board::with_hole(size, hole)
+            .with_obstacles(walls, pins)
+            .unwrap();
+        let table = DistanceTable::with_blocked(size, solved.blocked());
+        let solver = Solver::new(&table);
+
+        let mut board = solved.clone();
+        let scramble = [
+            Move::new(Pos3D::new(2, 1, 0), Direction3D::XNega),
+            Move::new(Pos3D::new(2, 0, 0), Direction3D::YPosi),
+            Move::new(Pos3D::new(2, 0, 1), Direction3D::ZNega),
+        ];
+        scramble
+            .iter()
+            .for_each(|mv| assert!(board.apply(*mv), "{:?}", mv));
+
+        let plan = solver.solve(&board, 10_000);
+        assert!(plan.solved);
+        assert_eq!(plan.moves.len(), scramble.len());
+        plan.moves
+            .iter()
+            .for_each(|mv| assert!(board.apply(*mv), "{:?}", mv));
+        assert_eq!(board, solved);
+    }
+
+    #[test]
+    fn fenced_in_cubes_are_never_solved() {
+        let size = Size3D::new(3, 3, 3);
+        let ring: HashSet<Pos3D> = [(0, 1), (1, 0), (1, 2), (2, 1)]
+            .into_iter()
+            .map(|(x, y)| Pos3D::new(x, y, 0))
+            .collect();
+        let mut parts = Board::with_hole(size, Pos3D::new(0, 0, 2)).parts().clone();
+        let (middle, corner) = (Pos3D::new(1, 1, 0), Pos3D::new(0, 0, 0));
+        parts.insert(middle, Cube::new(corner));
+        parts.insert(corner, Cube::new(middle));
+        let board = Board::new(size, parts)
+            .with_obstacles(ring, HashSet::new())
+            .unwrap();
+        let table = DistanceTable::with_blocked(size, board.blocked());
+
+        let plan = Solver::new(&table).solve(&board, 1_000);
+        assert!(!plan.solved);
+        assert_eq!(plan.expanded, 1_000);
+    }
+
+    #[test]
+    fn line_moves_count_once() {
+        let size = Size3D::new(4, 4, 4);
+        let table = DistanceTable::new(size);
+        let solved = Board::with_hole(size, Pos3D::new(1, 1, 0));
+        let mut board = solved.clone();
+        let scramble = [
+            Move::line(Pos3D::new(1, 3, 0), Direction3D::YNega, 2),
+            Move::line(Pos3D::new(3, 3, 0), Direction3D::XNega, 2),
+        ];
+        scramble
+            .iter()
+            .for_each(|mv| assert!(board.apply(*mv), "{:?}", mv));
+
+        let plan = Solver::new(&table)
+            .with_metric(Metric::Line)
+            .solve(&board, 10_000);
+        assert!(plan.solved);
+        assert_eq!(plan.moves.len(), scramble.len());
+        let mut solving = board.clone();
+        plan.moves
+            .iter()
+            .for_each(|mv| assert!(solving.apply(*mv), "{:?}", mv));
+        assert_eq!(solving, solved);
+
+        let plan = Solver::new(&table).solve(&board, 10_000);
+        assert!(plan.solved);
+        assert_eq!(plan.moves.len(), 4);
+        assert!(plan.moves.iter().all(|mv| mv.cubes == 1));
+    }
+
+    #[test]
+    fn budget_returns_progress() {
+        let size = Size3D::new(4, 4, 4);
+        let table = DistanceTable::new(size);
+        let solver = Solver::new(&table);
+        let mut board = Board::with_hole(size, Pos3D::new(0, 0, 0));
+        let before = board.disorder(&table);
+        assert!(board.apply(Move::new(Pos3D::new(1, 0, 0), Direction3D::XNega)));
+        assert!(board.apply(Move::new(Pos3D::new(2, 0, 0), Direction3D::XNega)));
+
+        let plan = solver.solve(&board, 1);
+        assert!(!plan.solved);
+        assert_eq!(plan.expanded, 1);
+        assert_eq!(plan.moves.len(), 1);
+        assert!(board.apply(plan.moves[0]));
+        assert!(board.disorder(&table) < before + 2);
+    }
+}
@@ -1,6 +1,9 @@
 use std::collections::HashMap;
 
 use alignment::{
+    belt::Turn,
+    board::{Board, Move},
+    goal::Goal,
     model::*,
     slide::{adjacents, move_one, slide},
 };
@@ -8,8 +11,11 @@ use rand::prelude::*;
 use tinyvec::*;
 
 pub fn rand_hole(size: Size3D) -> Pos3D {
-    let mut rng = rand::thread_rng();
+    rand_hole_with(&mut rand::thread_rng(), size)
+}
 
+/// A random surface cell drawn from `rng`, so a seeded generator always picks the same one.
+pub fn rand_hole_with<R: Rng>(rng: &mut R, size: Size3D) -> Pos3D {
     let ai = rng.gen_range(0..3);
     let ps: ArrayVec<[u8; 3]> = [size.x(), size.y(), size.z()]
         .into_iter()
@@ -52,12 +58,83 @@ pub fn simple_moves(size: Size3D, steps: u8) -> HashMap<Pos3D, Cube> {
     parts
 }
 
+/// How many times over the steps asked for a walk may run on while it keeps meeting the goal.
+const WALK_LIMIT: usize = 10;
+
+/// Walks `steps` random slides from a solved board with a random hole, drawn from `rng`, as
+/// `scramble_from` does; the board returned may still meet `goal` if the walk gave up.
+pub fn scramble<R: Rng>(rng: &mut R, goal: &Goal, steps: usize) -> Board {
+    let hole = rand_hole_with(rng, goal.size());
+    scramble_from(rng, goal, goal.solved(&[hole]), steps)
+}
+
+/// Walks `steps` random slides from `board`, which may have walls and pinned cubes.
+///
+/// No slide undoes the one before unless the hole is in a dead end. When the goal takes some cubes
+/// as alike a walk can still end on a board that meets it, so the walk goes on until it does not.
+/// After `WALK_LIMIT` times `steps` slides it gives up and returns the board it has reached, which
+/// then still meets the goal.
+pub fn scramble_from<R: Rng>(rng: &mut R, goal: &Goal, mut board: Board, steps: usize) -> Board {
+    let mut undo = None;
+    let mut walked = 0;
+    while walked < steps || (walked < steps * WALK_LIMIT && goal.is_met(&board)) {
+        let moves = board.moves();
+        let ahead: Vec<Move> = moves
+            .iter()
+            .copied()
+            .filter(|mv| Some(*mv) != undo)
+            .collect();
+        let choices = if ahead.is_empty() { moves } else { ahead };
+        if choices.is_empty() {
+            break;
+        }
+        let mv = choices[rng.gen_range(0..choices.len())];
+        let to = move_one(mv.src, board.size(), mv.direction).unwrap_or(mv.src);
+        board.apply(mv);
+        undo = Some(Move::new(to, mv.direction.invert()));
+        walked += 1;
+    }
+    board
+}
+
+/// Walks `steps` random belt turns from a solved box without a hole, drawn from `rng`, as
+/// `turn_from` does; the box returned may still meet `goal` if the walk gave up.
+pub fn scramble_belts<R: Rng>(rng: &mut R, goal: &Goal, steps: usize) -> Board {
+    turn_from(rng, goal, goal.solved(&[]), steps)
+}
+
+/// Walks `steps` random belt turns from `board`, turning no belt with an obstacle on it.
+///
+/// No turn undoes the one before, and as with slides the walk goes on while the goal is met. After
+/// `WALK_LIMIT` times `steps` turns it gives up and returns the board it has reached, which then
+/// still meets the goal.
+pub fn turn_from<R: Rng>(rng: &mut R, goal: &Goal, mut board: Board, steps: usize) -> Board {
+    let mut undo = None;
+    let mut walked = 0;
+    while walked < steps || (walked < steps * WALK_LIMIT && goal.is_met(&board)) {
+        let choices: Vec<Turn> = board
+            .turns()
+            .into_iter()
+            .filter(|turn| Some(*turn) != undo)
+            .collect();
+        if choices.is_empty() {
+            break;
+        }
+        let turn = choices[rng.gen_range(0..choices.len())];
+        board.turn(turn);
+        undo = Some(turn.invert());
+        walked += 1;
+    }
+    board
+}
+
 #[cfg(test)]
 mod test {
     use std::fs::File;
     use std::io::Write;
 
     use super::*;
+    use alignment::goal::{Axis, GoalShape, Requirement};
 
     #[test]
     fn assert_rand_hole() {
@@ -74,6 +151,79 @@ mod test {
         }
     }
 
+    #[test]
+    fn scramble_walks_away_from_goal() {
+        let size = Size3D::new(3, 3, 4);
+        let goal = GoalShape::Rotate(Axis::Z).goal(size).unwrap();
+        let mut rng = StdRng::seed_from_u64(7);
+        let board = scramble(&mut rng, &goal, 0);
+        assert!(goal.is_met(&board));
+        assert_eq!(board.holes().len(), 1);
+
+        let board = scramble(&mut rng, &goal, 40);
+        assert_eq!(board.holes().len(), 1);
+        assert_eq!(board.parts().len(), goal.cells().len() - 1);
+
+        let walls = [Pos3D::new(1, 1, 0)].into_iter().collect();
+        let pins = [Pos3D::new(1, 2, 0)].into_iter().collect();
+        let start = goal
+            .solved(&[Pos3D::new(0, 0, 0)])
+            .with_obstacles(walls, pins)
+            .unwrap();
+        let board = scramble_from(&mut rng, &goal, start.clone(), 40);
+        assert_eq!(board.walls(), start.walls());
+        assert_eq!(
+            board.get(Pos3D::new(1, 2, 0)),
+            start.get(Pos3D::new(1, 2, 0))
+        );
+
+        let goal = Goal::faces(size);
+        (0..20).for_each(|_| assert!(!goal.is_met(&scramble(&mut rng, &goal, 2))));
+
+        // A goal every board meets cannot be walked away from.
+        let cells = generate_surfaces(size)
+            .into_iter()
+            .map(|pos| (pos, Requirement::Any))
+            .collect();
+        let goal = Goal::new(size, cells, HashMap::new()).unwrap();
+        let board = scramble(&mut rng, &goal, 3);
+        assert!(goal.is_met(&board));
+        assert_eq!(board.holes().len(), 1);
+    }
+
+    #[test]
+    fn belts_scramble_without_a_hole() {
+        let size = Size3D::new(3, 3, 4);
+        let goal = Goal::identity(size);
+        let mut rng = StdRng::seed_from_u64(11);
+        let board = scramble_belts(&mut rng, &goal, 0);
+        assert!(board.is_solved());
+        assert!(board.holes().is_empty());
+
+        let board = scramble_belts(&mut rng, &goal, 20);
+        assert!(!goal.is_met(&board));
+        assert!(board.holes().is_empty());
+        assert_eq!(board.parts().len(), goal.cells().len());
+
+        let pins = [Pos3D::new(0, 0, 1)].into_iter().collect();
+        let start = goal
+            .solved(&[])
+            .with_obstacles(Default::default(), pins)
+            .unwrap();
+        let board = turn_from(&mut rng, &goal, start, 20);
+        assert_eq!(
+            board.get(Pos3D::new(0, 0, 1)),
+            Some(Cube::new(Pos3D::new(0, 0, 1)))
+        );
+
+        let cells = generate_surfaces(size)
+            .into_iter()
+            .map(|pos| (pos, Requirement::Any))
+            .collect();
+        let goal = Goal::new(size, cells, HashMap::new()).unwrap();
+        assert!(goal.is_met(&scramble_belts(&mut rng, &goal, 3)));
+    }
+
     #[test]
     fn hole_of_simple_moves() {
         let mut file = File::create(".shuffle.csv").unwrap();
@@ -0,0 +1,120 @@
+use getset::*;
+
+use crate::texture::Texture;
+
+/// Square tiles packed row by row into one RGBA8 image, so a renderer uploads a single texture
+/// instead of one per face plane.
+#[derive(Debug, Clone, PartialEq, Eq, Getters, CopyGetters)]
+pub struct Atlas {
+    #[getset(get_copy = "pub")]
+    texels: u32,
+    #[getset(get_copy = "pub")]
+    columns: u32,
+    #[getset(get_copy = "pub")]
+    rows: u32,
+    #[getset(get = "pub")]
+    data: Vec<u8>,
+}
+
+impl Atlas {
+    /// Room for `count` tiles of `texels` squared, shrinking the tiles until neither side of the
+    /// atlas exceeds `max_size`.
+    pub fn new(count: u32, texels: u32, max_size: u32) -> Self {
+        let columns = (count as f64).sqrt().ceil().max(1.0) as u32;
+        let rows = count.div_ceil(columns).max(1);
+        let texels = texels.min(max_size / columns.max(rows)).max(1);
+        let data = vec![0; (columns * texels * rows * texels) as usize * 4];
+        Atlas {
+            texels,
+            columns,
+            rows,
+            data,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.columns * self.texels
+    }
+
+    pub fn height(&self) -> u32 {
+        self.rows * self.texels
+    }
+
+    /// Copies `tile`, which must be `texels` wide, into slot `index`.
+    pub fn put(&mut self, index: u32, tile: &Texture) {
+        assert_eq!(tile.size(), self.texels, "tile size must match the atlas");
+        let (left, top) = self.origin(index);
+        let row_bytes = (self.texels * 4) as usize;
+        let width = self.width();
+        tile.data()
+            .chunks(row_bytes)
+            .enumerate()
+            .for_each(|(row, bytes)| {
+                let i = (((top + row as u32) * width + left) * 4) as usize;
+                self.data[i..(i + row_bytes)].copy_from_slice(bytes);
+            });
+    }
+
+    /// Texture coordinates of slot `index` as `(u0, v0, u1, v1)`, pulled in by half a texel so
+    /// filtering never samples the neighbours.
+    pub fn uv_rect(&self, index: u32) -> (f32, f32, f32, f32) {
+        let (left, top) = self.origin(index);
+        let (width, height) = (self.width() as f32, self.height() as f32);
+        let inset = 0.5;
+        (
+            (left as f32 + inset) / width,
+            (top as f32 + inset) / height,
+            ((left + self.texels) as f32 - inset) / width,
+            ((top + self.texels) as f32 - inset) / height,
+        )
+    }
+
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    fn origin(&self, index: u32) -> (u32, u32) {
+        assert!(index < self.columns * self.rows, "tile index out of range");
+        (
+            (index % self.columns) * self.texels,
+            (index / self.columns) * self.texels,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::texture::Rgba;
+
+    #[test]
+    fn atlas_packs_tiles() {
+        let mut atlas = Atlas::new(5, 4, 1024);
+        assert_eq!((atlas.columns(), atlas.rows(), atlas.texels()), (3, 2, 4));
+        assert_eq!((atlas.width(), atlas.height()), (12, 8));
+        assert_eq!(atlas.data().len(), 12 * 8 * 4);
+
+        let red = Texture::from_fn(4, |_, _| Rgba::rgb(255, 0, 0));
+        atlas.put(4, &red);
+        let at = |x: u32, y: u32| {
+            let i = ((y * atlas.width() + x) * 4) as usize;
+            atlas.data()[i]
+        };
+        assert_eq!(at(4, 4), 255);
+        assert_eq!(at(7, 7), 255);
+        assert_eq!(at(3, 4), 0);
+        assert_eq!(at(8, 4), 0);
+
+        let (u0, v0, u1, v1) = atlas.uv_rect(4);
+        assert!(u0 > 4.0 / 12.0 && u1 < 8.0 / 12.0);
+        assert!(v0 > 4.0 / 8.0 && v1 < 1.0);
+    }
+
+    #[test]
+    fn atlas_shrinks_to_fit() {
+        let atlas = Atlas::new(1728, 100, 4096);
+        assert_eq!(atlas.columns(), 42);
+        assert!(atlas.width() <= 4096 && atlas.height() <= 4096);
+        assert_eq!(atlas.texels(), 97);
+    }
+}
@@ -0,0 +1,116 @@
+use alignment::model::*;
+use getset::*;
+
+/// Where one cube sits on one face of the box, and which way the face's axes run across its
+/// texture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, CopyGetters)]
+pub struct FaceLayout {
+    /// Cells across the whole face.
+    #[getset(get_copy = "pub")]
+    width: u8,
+    #[getset(get_copy = "pub")]
+    height: u8,
+    /// Cell of the cube on the face.
+    #[getset(get_copy = "pub")]
+    pos_x: u8,
+    #[getset(get_copy = "pub")]
+    pos_y: u8,
+    sig_x: bool,
+    sig_y: bool,
+}
+
+impl FaceLayout {
+    pub fn new(spaces: Size3D, pos: Pos3D, direction: Direction3D, reversed: bool) -> Self {
+        let (width, height) = match direction {
+            Direction3D::XNega => (spaces.z(), spaces.y()),
+            Direction3D::XPosi => (spaces.z(), spaces.y()),
+            Direction3D::YNega => (spaces.z(), spaces.x()),
+            Direction3D::YPosi => (spaces.z(), spaces.x()),
+            Direction3D::ZNega => (spaces.y(), spaces.x()),
+            Direction3D::ZPosi => (spaces.y(), spaces.x()),
+        };
+
+        let (pos_x, pos_y) = match direction {
+            Direction3D::XNega => (pos.z(), pos.y()),
+            Direction3D::XPosi => (pos.z(), pos.y()),
+            Direction3D::YNega => (pos.z(), pos.x()),
+            Direction3D::YPosi => (pos.z(), pos.x()),
+            Direction3D::ZNega => (pos.y(), pos.x()),
+            Direction3D::ZPosi => (pos.y(), pos.x()),
+        };
+
+        let (sig_x, sig_y) = match direction {
+            Direction3D::XNega => (false, !reversed),
+            Direction3D::XPosi => (false, reversed),
+            Direction3D::YNega => (false, reversed),
+            Direction3D::YPosi => (false, !reversed),
+            Direction3D::ZNega => (reversed, true),
+            Direction3D::ZPosi => (!reversed, true),
+        };
+
+        FaceLayout {
+            width,
+            height,
+            pos_x,
+            pos_y,
+            sig_x,
+            sig_y,
+        }
+    }
+
+    /// Position of texel `(x, y)` of a `texels` wide cube texture on the whole face, in texels.
+    pub fn pixel(&self, texels: f32, x: u32, y: u32) -> (f32, f32) {
+        let normalize = |pos: u8, p: u32, sig: bool| -> f32 {
+            let base = texels * pos as f32;
+            if sig {
+                base + p as f32
+            } else {
+                base + (texels - p as f32 - 1.0)
+            }
+        };
+        (
+            normalize(self.pos_x, x, self.sig_x),
+            normalize(self.pos_y, y, self.sig_y),
+        )
+    }
+
+    /// Size of the whole face in texels.
+    pub fn face_pixels(&self, texels: f32) -> (f32, f32) {
+        (texels * self.width as f32, texels * self.height as f32)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn faces_tile_without_gaps() {
+        let spaces = Size3D::new(3, 4, 5);
+        let texels = 4;
+        for reversed in [false, true] {
+            let mut seen = vec![];
+            (0..spaces.y()).for_each(|y| {
+                (0..spaces.z()).for_each(|z| {
+                    let layout =
+                        FaceLayout::new(spaces, Pos3D::new(0, y, z), Direction3D::XNega, reversed);
+                    assert_eq!((layout.width(), layout.height()), (5, 4));
+                    (0..texels).for_each(|tx| {
+                        (0..texels).for_each(|ty| {
+                            seen.push(layout.pixel(texels as f32, tx, ty));
+                        })
+                    });
+                })
+            });
+            let (w, h) = FaceLayout::new(spaces, Pos3D::new(0, 0, 0), Direction3D::XNega, reversed)
+                .face_pixels(texels as f32);
+            assert_eq!(seen.len(), (w * h) as usize);
+            seen.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            seen.dedup();
+            assert_eq!(seen.len(), (w * h) as usize);
+            assert!(seen
+                .iter()
+                .all(|(x, y)| *x >= 0.0 && *x < w && *y >= 0.0 && *y < h));
+        }
+    }
+}
@@ -0,0 +1,5 @@
+pub mod atlas;
+pub mod layout;
+pub mod palette;
+pub mod pattern;
+pub mod texture;
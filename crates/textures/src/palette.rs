@@ -0,0 +1,186 @@
+use alignment::model::Direction3D;
+
+use crate::texture::Rgba;
+
+/// Colours of the six faces, in `Direction3D` order, and the paper they are printed on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    pub faces: [Rgba; 6],
+    pub paper: Rgba,
+    /// Hatch each face with its own motif, so faces stay apart without telling colours apart.
+    pub cues: bool,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Preset::Classic.palette()
+    }
+}
+
+impl Palette {
+    pub fn face(&self, direction: Direction3D) -> Rgba {
+        self.faces[direction as usize]
+    }
+
+    /// The preset this palette matches, ignoring cues, or `None` for a custom one.
+    pub fn preset(&self) -> Option<Preset> {
+        Preset::ALL.into_iter().find(|preset| {
+            let palette = preset.palette();
+            palette.faces == self.faces && palette.paper == self.paper
+        })
+    }
+
+    /// Reads a preset name, or six comma separated `#rrggbb` face colours optionally followed by
+    /// the paper colour.
+    pub fn parse(value: &str) -> Option<Self> {
+        if let Some(preset) = Preset::parse(value) {
+            return Some(preset.palette());
+        }
+        let colors: Vec<Rgba> = value
+            .split(',')
+            .map(Rgba::parse_hex)
+            .collect::<Option<_>>()?;
+        let (faces, paper) = match colors.len() {
+            6 => (&colors[..], Rgba::WHITE),
+            7 => (&colors[..6], colors[6]),
+            _ => return None,
+        };
+        Some(Palette {
+            faces: faces.try_into().ok()?,
+            paper,
+            cues: false,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Preset {
+    /// Red, cyan, green, purple, blue and yellow on white.
+    Classic,
+    /// The Okabe-Ito set, told apart under the common colour-vision deficiencies.
+    OkabeIto,
+    /// Saturated colours on black.
+    HighContrast,
+    /// Six grey levels on white, hatched per face.
+    Greyscale,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 4] = [
+        Preset::Classic,
+        Preset::OkabeIto,
+        Preset::HighContrast,
+        Preset::Greyscale,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Preset::Classic => "classic",
+            Preset::OkabeIto => "okabe-ito",
+            Preset::HighContrast => "high-contrast",
+            Preset::Greyscale => "greyscale",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Preset::ALL.into_iter().find(|preset| preset.name() == name)
+    }
+
+    #[must_use]
+    pub fn next(self) -> Self {
+        let i = Preset::ALL
+            .iter()
+            .position(|p| *p == self)
+            .unwrap_or_default();
+        Preset::ALL[(i + 1) % Preset::ALL.len()]
+    }
+
+    #[must_use]
+    pub fn prev(self) -> Self {
+        let i = Preset::ALL
+            .iter()
+            .position(|p| *p == self)
+            .unwrap_or_default();
+        Preset::ALL[(i + Preset::ALL.len() - 1) % Preset::ALL.len()]
+    }
+
+    pub fn palette(self) -> Palette {
+        let (faces, paper, cues) = match self {
+            Preset::Classic => (
+                [
+                    Rgba::rgb(255, 0, 0),
+                    Rgba::rgb(0, 255, 255),
+                    Rgba::rgb(0, 255, 0),
+                    Rgba::rgb(127, 0, 127),
+                    Rgba::rgb(0, 0, 255),
+                    Rgba::rgb(255, 255, 0),
+                ],
+                Rgba::WHITE,
+                false,
+            ),
+            Preset::OkabeIto => (
+                [
+                    Rgba::rgb(213, 94, 0),
+                    Rgba::rgb(86, 180, 233),
+                    Rgba::rgb(0, 158, 115),
+                    Rgba::rgb(204, 121, 167),
+                    Rgba::rgb(0, 114, 178),
+                    Rgba::rgb(240, 228, 66),
+                ],
+                Rgba::WHITE,
+                false,
+            ),
+            Preset::HighContrast => (
+                [
+                    Rgba::rgb(255, 128, 0),
+                    Rgba::WHITE,
+                    Rgba::rgb(0, 255, 0),
+                    Rgba::rgb(255, 0, 255),
+                    Rgba::rgb(0, 255, 255),
+                    Rgba::rgb(255, 255, 0),
+                ],
+                Rgba::BLACK,
+                false,
+            ),
+            Preset::Greyscale => (
+                [
+                    Rgba::rgb(0, 0, 0),
+                    Rgba::rgb(40, 40, 40),
+                    Rgba::rgb(80, 80, 80),
+                    Rgba::rgb(120, 120, 120),
+                    Rgba::rgb(160, 160, 160),
+                    Rgba::rgb(200, 200, 200),
+                ],
+                Rgba::WHITE,
+                true,
+            ),
+        };
+        Palette { faces, paper, cues }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_palettes() {
+        for preset in Preset::ALL {
+            assert_eq!(Palette::parse(preset.name()), Some(preset.palette()));
+            assert_eq!(preset.palette().preset(), Some(preset));
+            assert_eq!(preset.next().prev(), preset);
+        }
+
+        let custom = Palette::parse("#000000,#111111,#222222,#333333,#444444,#555555").unwrap();
+        assert_eq!(custom.face(Direction3D::YNega), Rgba::rgb(0x22, 0x22, 0x22));
+        assert_eq!(custom.paper, Rgba::WHITE);
+        assert_eq!(custom.preset(), None);
+
+        let custom = Palette::parse("000000,111111,222222,333333,444444,555555,#ff8000").unwrap();
+        assert_eq!(custom.paper, Rgba::rgb(255, 128, 0));
+
+        assert_eq!(Palette::parse("#000000,#111111"), None);
+        assert_eq!(Palette::parse("#00000g,1,2,3,4,5"), None);
+        assert_eq!(Palette::parse("sepia"), None);
+    }
+}
@@ -0,0 +1,457 @@
+use alignment::model::*;
+use derive_new::new;
+
+use crate::layout::FaceLayout;
+use crate::palette::Palette;
+use crate::texture::{Picture, Rgba, Texture};
+
+/// Procedural art for the face planes. Every pattern except the labels runs continuously across a
+/// whole face, so it only lines up once the cubes are back home.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Pattern {
+    Rings,
+    Stripes,
+    Gradient,
+    Checker,
+    /// The home cube's position in the surface ordering.
+    HomeIndex,
+    /// Row and column of the home cube on this face.
+    RowColumn,
+}
+
+/// One side of one face plane of one cube.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, new)]
+pub struct FaceSpec {
+    pub spaces: Size3D,
+    pub home: Pos3D,
+    pub direction: Direction3D,
+    pub reversed: bool,
+}
+
+impl FaceSpec {
+    pub fn layout(&self) -> FaceLayout {
+        FaceLayout::new(self.spaces, self.home, self.direction, self.reversed)
+    }
+
+    /// Whether the other side of the plane paints this side's texture turned over top to bottom,
+    /// rather than left to right. Only the labels read the same from both sides.
+    pub fn mirrors_rows(&self) -> bool {
+        matches!(self.direction, Direction3D::ZNega | Direction3D::ZPosi)
+    }
+}
+
+impl Pattern {
+    pub const ALL: [Pattern; 6] = [
+        Pattern::Rings,
+        Pattern::Stripes,
+        Pattern::Gradient,
+        Pattern::Checker,
+        Pattern::HomeIndex,
+        Pattern::RowColumn,
+    ];
+
+    pub fn render(self, texels: u32, palette: &Palette, spec: FaceSpec) -> Texture {
+        let layout = spec.layout();
+        let color = palette.face(spec.direction);
+        let paper = palette.paper;
+        let t = texels as f32;
+        let (face_width, face_height) = layout.face_pixels(t);
+
+        // Colour of the pattern at a point on the face, before any cue is hatched on top.
+        let paint = |pixel_x: f32, pixel_y: f32| -> Rgba {
+            match self {
+                Pattern::Rings => {
+                    let d_x = pixel_x - face_width / 2.0;
+                    let d_y = pixel_y - face_height / 2.0;
+                    let d = (d_x.powf(2.0) + d_y.powf(2.0)).sqrt();
+                    let u = (d / (t / 2.0)) as u8;
+                    if u % 2 == 1 {
+                        color
+                    } else {
+                        paper
+                    }
+                }
+                Pattern::Stripes => {
+                    let band = ((pixel_x + pixel_y) / (t / 2.0)) as u32;
+                    if band % 2 == 1 {
+                        color
+                    } else {
+                        paper
+                    }
+                }
+                Pattern::Gradient => {
+                    paper.mix(color, (pixel_x / face_width + pixel_y / face_height) / 2.0)
+                }
+                _ => {
+                    let square = (pixel_x / (t / 2.0)) as u32 + (pixel_y / (t / 2.0)) as u32;
+                    if square % 2 == 1 {
+                        color
+                    } else {
+                        paper
+                    }
+                }
+            }
+        };
+
+        match self {
+            Pattern::Rings | Pattern::Stripes | Pattern::Gradient | Pattern::Checker => {
+                Texture::from_fn(texels, |column, row| {
+                    // Texels are laid out with the face's first axis running down the rows.
+                    let (pixel_x, pixel_y) = layout.pixel(t, row, column);
+                    let c = paint(pixel_x, pixel_y);
+                    if palette.cues && c != paper && cue(spec.direction, pixel_x, pixel_y, t / 4.0)
+                    {
+                        c.mix(c.contrasting(), 0.6)
+                    } else {
+                        c
+                    }
+                })
+            }
+            Pattern::HomeIndex => {
+                let index = generate_surfaces(spec.spaces)
+                    .iter()
+                    .position(|pos| *pos == spec.home)
+                    .unwrap_or_default();
+                draw_label(texels, &index.to_string(), color)
+            }
+            Pattern::RowColumn => {
+                let label = format!("{}-{}", layout.pos_y() + 1, layout.pos_x() + 1);
+                draw_label(texels, &label, color)
+            }
+        }
+    }
+}
+
+/// The motif hatched over a face's colour when cues are on: lines across, lines down, the two
+/// diagonals, dots and a grid, each repeating every `pitch` texels of the face.
+fn cue(direction: Direction3D, x: f32, y: f32, pitch: f32) -> bool {
+    let line = |v: f32| v.rem_euclid(pitch) < pitch / 4.0;
+    match direction {
+        Direction3D::XNega => line(y),
+        Direction3D::XPosi => line(x),
+        Direction3D::YNega => line(x + y),
+        Direction3D::YPosi => line(x - y),
+        Direction3D::ZNega => {
+            let d_x = x.rem_euclid(pitch) - pitch / 2.0;
+            let d_y = y.rem_euclid(pitch) - pitch / 2.0;
+            (d_x.powf(2.0) + d_y.powf(2.0)).sqrt() < pitch / 4.0
+        }
+        Direction3D::ZPosi => line(x) || line(y),
+    }
+}
+
+/// 3x5 glyphs for the characters a label may hold, one bit per texel, top row first.
+fn glyph(c: char) -> u16 {
+    match c {
+        '0' => 0b111_101_101_101_111,
+        '1' => 0b010_110_010_010_111,
+        '2' => 0b111_001_111_100_111,
+        '3' => 0b111_001_111_001_111,
+        '4' => 0b101_101_111_001_001,
+        '5' => 0b111_100_111_001_111,
+        '6' => 0b111_100_111_101_111,
+        '7' => 0b111_001_010_010_010,
+        '8' => 0b111_101_111_101_111,
+        '9' => 0b111_101_111_001_111,
+        '-' => 0b000_000_111_000_000,
+        _ => 0,
+    }
+}
+
+/// Prints `label` centred on `paper`, in whichever of black or white reads better.
+pub fn draw_label(texels: u32, label: &str, paper: Rgba) -> Texture {
+    const GLYPH_WIDTH: usize = 3;
+    const GLYPH_HEIGHT: usize = 5;
+    const GLYPH_GAP: usize = 1;
+
+    let size = texels as usize;
+    let chars: Vec<char> = label.chars().collect();
+    let columns = (chars.len() * (GLYPH_WIDTH + GLYPH_GAP)).saturating_sub(GLYPH_GAP);
+    let scale = ((size * 8 / 10) / columns.max(1))
+        .min(size * 6 / 10 / GLYPH_HEIGHT)
+        .max(1);
+    let left = size.saturating_sub(columns * scale) / 2;
+    let top = size.saturating_sub(GLYPH_HEIGHT * scale) / 2;
+
+    let is_ink = |x: usize, y: usize| -> bool {
+        if x < left || y < top {
+            return false;
+        }
+        let (gx, gy) = ((x - left) / scale, (y - top) / scale);
+        let (index, column) = (
+            gx / (GLYPH_WIDTH + GLYPH_GAP),
+            gx % (GLYPH_WIDTH + GLYPH_GAP),
+        );
+        if gy >= GLYPH_HEIGHT || column >= GLYPH_WIDTH || index >= chars.len() {
+            return false;
+        }
+        let bit = (GLYPH_HEIGHT - 1 - gy) * GLYPH_WIDTH + (GLYPH_WIDTH - 1 - column);
+        glyph(chars[index]) & (1 << bit) != 0
+    };
+
+    let ink = paper.contrasting();
+    Texture::from_fn(texels, |column, row| {
+        if is_ink(column as usize, row as usize) {
+            ink
+        } else {
+            paper
+        }
+    })
+}
+
+/// Pixel rectangle of a picture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, new)]
+pub struct Tile {
+    pub left: u32,
+    pub top: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Tile {
+    pub fn whole(picture: &Picture) -> Self {
+        Tile::new(0, 0, picture.width(), picture.height())
+    }
+
+    /// Tile `index` of a picture cut into a grid of `columns` by `rows`, counted row by row.
+    pub fn grid(picture: &Picture, columns: u32, rows: u32, index: u32) -> Self {
+        let (width, height) = (picture.width() / columns, picture.height() / rows);
+        Tile::new(
+            (index % columns) * width,
+            (index / columns) * height,
+            width,
+            height,
+        )
+    }
+}
+
+/// Cuts this cube's share out of `tile` of `picture`, so the solved face shows the picture whole.
+pub fn slice(texels: u32, spec: FaceSpec, picture: &Picture, tile: Tile) -> Texture {
+    let layout = spec.layout();
+    let t = texels as f32;
+    let (face_width, face_height) = layout.face_pixels(t);
+
+    Texture::from_fn(texels, |column, row| {
+        let (pixel_x, pixel_y) = layout.pixel(t, row, column);
+        let sx = ((pixel_x / face_width) * tile.width as f32) as u32;
+        let sy = ((pixel_y / face_height) * tile.height as f32) as u32;
+        let sx = tile.left + sx.min(tile.width.saturating_sub(1));
+        let sy = tile.top + sy.min(tile.height.saturating_sub(1));
+        picture.get(sx, sy).unwrap_or(Rgba::BLACK)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use strum::IntoEnumIterator;
+
+    fn face_cells(spaces: Size3D, direction: Direction3D) -> Vec<Pos3D> {
+        generate_surfaces(spaces)
+            .into_iter()
+            .filter(|pos| pos.get_faces(spaces).contains(&direction))
+            .collect()
+    }
+
+    #[test]
+    fn patterns_use_the_palette() {
+        let spaces = Size3D::new(3, 4, 5);
+        let palette = Palette::default();
+        for pattern in Pattern::ALL {
+            for direction in [Direction3D::XNega, Direction3D::ZPosi] {
+                let texture = pattern.render(
+                    16,
+                    &palette,
+                    FaceSpec::new(spaces, Pos3D::new(0, 0, 0), direction, false),
+                );
+                assert_eq!(texture.size(), 16);
+                assert_eq!(texture.data().len(), 16 * 16 * 4);
+                let color = palette.face(direction);
+                let allowed = |c: Rgba| {
+                    c == color
+                        || c == palette.paper
+                        || c == color.contrasting()
+                        || pattern == Pattern::Gradient
+                };
+                assert!((0..16).all(|r| (0..16).all(|c| allowed(texture.get(c, r).unwrap()))));
+            }
+        }
+    }
+
+    #[test]
+    fn rings_cover_whole_face() {
+        let spaces = Size3D::new(3, 4, 5);
+        let palette = Palette::default();
+        let color = palette.face(Direction3D::YNega);
+        let colored: usize = face_cells(spaces, Direction3D::YNega)
+            .into_iter()
+            .map(|home| {
+                let texture = Pattern::Rings.render(
+                    10,
+                    &palette,
+                    FaceSpec::new(spaces, home, Direction3D::YNega, false),
+                );
+                (0..10)
+                    .flat_map(|r| (0..10).map(move |c| (c, r)))
+                    .filter(|(c, r)| texture.get(*c, *r) == Some(color))
+                    .count()
+            })
+            .sum();
+        assert!(colored > 0);
+    }
+
+    #[test]
+    fn cues_tell_faces_apart() {
+        let spaces = Size3D::new(4, 4, 4);
+        let mut palette = Palette {
+            faces: [Rgba::rgb(90, 90, 90); 6],
+            cues: true,
+            ..Default::default()
+        };
+
+        let textures: Vec<Texture> = Direction3D::iter()
+            .map(|direction| {
+                let home = face_cells(spaces, direction)[0];
+                Pattern::Gradient.render(
+                    32,
+                    &palette,
+                    FaceSpec::new(spaces, home, direction, false),
+                )
+            })
+            .collect();
+        for (i, a) in textures.iter().enumerate() {
+            for b in textures.iter().skip(i + 1) {
+                assert_ne!(a, b);
+            }
+        }
+
+        palette.cues = false;
+        let plain = Pattern::Rings.render(
+            32,
+            &palette,
+            FaceSpec::new(spaces, Pos3D::new(0, 0, 0), Direction3D::XNega, false),
+        );
+        let colors = (0..32)
+            .flat_map(|r| (0..32).map(move |c| (c, r)))
+            .filter_map(|(c, r)| plain.get(c, r))
+            .all(|c| c == palette.paper || c == palette.faces[0]);
+        assert!(colors);
+    }
+
+    #[test]
+    fn labels() {
+        let paper = Rgba::rgb(0, 0, 255);
+        let texture = draw_label(20, "12", paper);
+        let ink = (0..20)
+            .flat_map(|r| (0..20).map(move |c| (c, r)))
+            .filter(|(c, r)| texture.get(*c, *r) == Some(Rgba::WHITE))
+            .count();
+        assert!(ink > 0);
+        assert_eq!(texture.get(0, 0), Some(paper));
+        assert_eq!(
+            draw_label(20, "", paper),
+            Texture::from_fn(20, |_, _| paper)
+        );
+    }
+
+    #[test]
+    fn slices_reassemble_picture() {
+        let spaces = Size3D::new(3, 4, 5);
+        let direction = Direction3D::ZNega;
+        let layout = FaceLayout::new(spaces, Pos3D::new(0, 0, 0), direction, false);
+        let texels = 4;
+        let (width, height) = layout.face_pixels(texels as f32);
+        let (width, height) = (width as u32, height as u32);
+
+        // Every texel of the picture is unique, so each slice must copy it exactly once.
+        let data: Vec<u8> = (0..(width * height))
+            .flat_map(|i| [(i % 256) as u8, (i / 256) as u8, 0, 255])
+            .collect();
+        let picture = Picture::new(width, height, &data);
+
+        let mut seen: Vec<Rgba> = face_cells(spaces, direction)
+            .into_iter()
+            .flat_map(|home| {
+                let texture = slice(
+                    texels,
+                    FaceSpec::new(spaces, home, direction, false),
+                    &picture,
+                    Tile::whole(&picture),
+                );
+                (0..texels)
+                    .flat_map(|r| (0..texels).map(move |c| (c, r)))
+                    .map(|(c, r)| texture.get(c, r).unwrap())
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        assert_eq!(seen.len(), (width * height) as usize);
+        seen.sort_by_key(|c| (c.g, c.r));
+        seen.dedup();
+        assert_eq!(seen.len(), (width * height) as usize);
+    }
+
+    #[test]
+    fn reversed_sides_mirror_the_outward_side() {
+        let spaces = Size3D::new(3, 4, 5);
+        let palette = Palette {
+            cues: true,
+            ..Default::default()
+        };
+        let data: Vec<u8> = (0..(20 * 20))
+            .flat_map(|i| [(i % 256) as u8, (i / 256) as u8, 0, 255])
+            .collect();
+        let picture = Picture::new(20, 20, &data);
+        let texels = 8;
+        for direction in Direction3D::iter() {
+            let front = FaceSpec::new(spaces, face_cells(spaces, direction)[1], direction, false);
+            let back = FaceSpec {
+                reversed: true,
+                ..front
+            };
+            let mirror = |(c, r): (u32, u32)| {
+                if front.mirrors_rows() {
+                    (c, texels - 1 - r)
+                } else {
+                    (texels - 1 - c, r)
+                }
+            };
+            let mut pairs: Vec<(Texture, Texture)> = [
+                Pattern::Rings,
+                Pattern::Stripes,
+                Pattern::Gradient,
+                Pattern::Checker,
+            ]
+            .into_iter()
+            .map(|pattern| {
+                (
+                    pattern.render(texels, &palette, front),
+                    pattern.render(texels, &palette, back),
+                )
+            })
+            .collect();
+            let tile = Tile::whole(&picture);
+            pairs.push((
+                slice(texels, front, &picture, tile),
+                slice(texels, back, &picture, tile),
+            ));
+            for (front, back) in pairs {
+                assert_ne!(front, back);
+                (0..texels)
+                    .flat_map(|r| (0..texels).map(move |c| (c, r)))
+                    .for_each(|(c, r)| {
+                        let (mc, mr) = mirror((c, r));
+                        assert_eq!(back.get(c, r), front.get(mc, mr), "{:?}", direction);
+                    });
+            }
+        }
+    }
+
+    #[test]
+    fn grid_tiles() {
+        let data = vec![0; 30 * 20 * 4];
+        let picture = Picture::new(30, 20, &data);
+        assert_eq!(Tile::grid(&picture, 3, 2, 0), Tile::new(0, 0, 10, 10));
+        assert_eq!(Tile::grid(&picture, 3, 2, 4), Tile::new(10, 10, 10, 10));
+        assert_eq!(Tile::whole(&picture), Tile::new(0, 0, 30, 20));
+    }
+}
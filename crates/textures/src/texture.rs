@@ -0,0 +1,132 @@
+use derive_new::new;
+use getset::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, new)]
+pub struct Rgba {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Rgba {
+    pub const BLACK: Rgba = Rgba::rgb(0, 0, 0);
+    pub const WHITE: Rgba = Rgba::rgb(255, 255, 255);
+
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Rgba { r, g, b, a: 255 }
+    }
+
+    /// Reads `#rrggbb` or `rrggbb`.
+    pub fn parse_hex(value: &str) -> Option<Self> {
+        let hex = value.trim().trim_start_matches('#');
+        if hex.len() != 6 || !hex.is_ascii() {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..(i + 2)], 16).ok();
+        Some(Rgba::rgb(channel(0)?, channel(2)?, channel(4)?))
+    }
+
+    /// Perceived brightness in `0..=1`.
+    pub fn luminance(self) -> f32 {
+        (0.299 * self.r as f32 + 0.587 * self.g as f32 + 0.114 * self.b as f32) / 255.0
+    }
+
+    /// Whichever of black or white reads better on top of this colour.
+    pub fn contrasting(self) -> Self {
+        if self.luminance() > 0.5 {
+            Rgba::BLACK
+        } else {
+            Rgba::WHITE
+        }
+    }
+
+    #[must_use]
+    pub fn mix(self, other: Rgba, t: f32) -> Self {
+        let t = t.clamp(0.0, 1.0);
+        let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+        Rgba::new(
+            mix(self.r, other.r),
+            mix(self.g, other.g),
+            mix(self.b, other.b),
+            mix(self.a, other.a),
+        )
+    }
+}
+
+/// A square RGBA8 image, stored row by row.
+#[derive(Debug, Clone, PartialEq, Eq, Getters, CopyGetters)]
+pub struct Texture {
+    #[getset(get_copy = "pub")]
+    size: u32,
+    #[getset(get = "pub")]
+    data: Vec<u8>,
+}
+
+impl Texture {
+    /// Paints every texel with `f(column, row)`.
+    pub fn from_fn(size: u32, mut f: impl FnMut(u32, u32) -> Rgba) -> Self {
+        let mut data = Vec::with_capacity((size * size) as usize * 4);
+        for row in 0..size {
+            for column in 0..size {
+                let c = f(column, row);
+                data.extend_from_slice(&[c.r, c.g, c.b, c.a]);
+            }
+        }
+        Texture { size, data }
+    }
+
+    pub fn get(&self, column: u32, row: u32) -> Option<Rgba> {
+        let i = ((row * self.size + column) * 4) as usize;
+        (column < self.size)
+            .then(|| self.data.get(i..(i + 4)))
+            .flatten()
+            .map(|c| Rgba::new(c[0], c[1], c[2], c[3]))
+    }
+
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+/// A borrowed RGBA8 picture of any shape to cut face textures from.
+#[derive(Debug, Clone, Copy, new, CopyGetters)]
+pub struct Picture<'a> {
+    #[getset(get_copy = "pub")]
+    width: u32,
+    #[getset(get_copy = "pub")]
+    height: u32,
+    data: &'a [u8],
+}
+
+impl Picture<'_> {
+    pub fn get(&self, x: u32, y: u32) -> Option<Rgba> {
+        let i = ((y * self.width + x) * 4) as usize;
+        (x < self.width)
+            .then(|| self.data.get(i..(i + 4)))
+            .flatten()
+            .map(|c| Rgba::new(c[0], c[1], c[2], c[3]))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn texture_from_fn() {
+        let texture = Texture::from_fn(3, |c, r| Rgba::rgb(c as u8, r as u8, 0));
+        assert_eq!(texture.data().len(), 3 * 3 * 4);
+        assert_eq!(texture.get(2, 1), Some(Rgba::rgb(2, 1, 0)));
+        assert_eq!(texture.get(3, 0), None);
+        assert_eq!(texture.get(0, 3), None);
+    }
+
+    #[test]
+    fn colours() {
+        assert_eq!(Rgba::WHITE.contrasting(), Rgba::BLACK);
+        assert_eq!(Rgba::rgb(0, 0, 255).contrasting(), Rgba::WHITE);
+        assert_eq!(Rgba::BLACK.mix(Rgba::WHITE, 0.5), Rgba::rgb(128, 128, 128));
+        assert_eq!(Rgba::BLACK.mix(Rgba::WHITE, 2.0), Rgba::WHITE);
+    }
+}
@@ -0,0 +1,340 @@
+use crate::clock::Clock;
+use crate::components::*;
+use crate::events::CubeSlid;
+use crate::resources::{BoardResource, CubesResource};
+use alignment::model::Direction3D;
+use bevy::prelude::*;
+use std::collections::VecDeque;
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Easing {
+    Linear,
+    QuadInOut,
+    CubicInOut,
+    SineInOut,
+    BackOut,
+}
+
+impl Easing {
+    /// Maps the linear progress `t` in `0..=1` onto the curve, keeping both ends fixed.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::QuadInOut => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(2) / 2.0
+                }
+            }
+            Easing::CubicInOut => {
+                if t < 0.5 {
+                    4.0 * t.powi(3)
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+            Easing::SineInOut => -((PI * t).cos() - 1.0) / 2.0,
+            Easing::BackOut => {
+                let c1 = 1.70158;
+                let c3 = c1 + 1.0;
+                1.0 + c3 * (t - 1.0).powi(3) + c1 * (t - 1.0).powi(2)
+            }
+        }
+    }
+
+    #[must_use]
+    pub fn next(self) -> Self {
+        match self {
+            Easing::Linear => Easing::QuadInOut,
+            Easing::QuadInOut => Easing::CubicInOut,
+            Easing::CubicInOut => Easing::SineInOut,
+            Easing::SineInOut => Easing::BackOut,
+            Easing::BackOut => Easing::Linear,
+        }
+    }
+}
+
+pub struct AnimationSettings {
+    /// Seconds one slide takes from start to end.
+    pub duration: f32,
+    pub easing: Easing,
+}
+
+impl Default for AnimationSettings {
+    fn default() -> Self {
+        AnimationSettings {
+            duration: 1.0,
+            easing: Easing::QuadInOut,
+        }
+    }
+}
+
+/// Pivot and axis of a slide along an edge of the box onto or off a face across its way, so the
+/// cube swings around the edge instead of sliding straight.
+pub struct EdgeArc {
+    pub pivot: Vec3,
+    pub axis: Vec3,
+}
+
+pub struct MovingCube {
+    pub entity: Entity,
+    pub start: Vec3,
+    pub end: Vec3,
+    pub arc: Option<EdgeArc>,
+    pub elapsed: f32,
+}
+
+impl MovingCube {
+    /// How far the body leans into the swing halfway around an edge.
+    const EDGE_TILT: f32 = FRAC_PI_4;
+
+    fn new(entity: Entity, resource: &CubesResource, event: &CubeSlid) -> Self {
+        let start = resource.calc_center(event.from);
+        let end = resource.calc_center(event.to);
+
+        let from_faces = event.from.get_faces(resource.spaces);
+        let to_faces = event.to.get_faces(resource.spaces);
+        // Only a slide that leaves or joins the face across its way turns a corner of the box; it
+        // then swings around the edge it runs along, if it lies on one.
+        let ahead = event.direction;
+        let behind = ahead.invert();
+        let turns = (from_faces.contains(&behind) && !to_faces.contains(&behind))
+            || (to_faces.contains(&ahead) && !from_faces.contains(&ahead));
+        let side = from_faces
+            .iter()
+            .find(|face| **face != ahead && **face != behind && to_faces.contains(*face));
+        let arc = match (turns, side) {
+            (true, Some(side)) => {
+                // A quarter turn about the edge through the middle of both cells, bulging out of
+                // the side face.
+                let (way, out) = (unit_vec(ahead), unit_vec(*side));
+                let axis = out.cross(way).normalize_or_zero();
+                (axis != Vec3::ZERO).then(|| EdgeArc {
+                    pivot: start + (way - out) * resource.cube_size / 2.0,
+                    axis,
+                })
+            }
+            _ => None,
+        };
+
+        MovingCube {
+            entity,
+            start,
+            end,
+            arc,
+            elapsed: 0.0,
+        }
+    }
+
+    fn place(&self, progress: f32, tr: &mut Transform) {
+        match &self.arc {
+            None => {
+                tr.translation = self.start.lerp(self.end, progress);
+            }
+            Some(arc) => {
+                let swing = Quat::from_axis_angle(arc.axis, FRAC_PI_2 * progress);
+                tr.translation = arc.pivot + swing * (self.start - arc.pivot);
+                tr.rotation =
+                    Quat::from_axis_angle(arc.axis, Self::EDGE_TILT * (PI * progress).sin());
+            }
+        }
+    }
+
+    fn finish(&self, tr: &mut Transform) {
+        tr.translation = self.end;
+        tr.rotation = Quat::IDENTITY;
+    }
+}
+
+/// Moves on the board not fully drawn yet, in the order they were made, each with every cube it
+/// shifted; the cubes of one move are drawn together.
+#[derive(Default)]
+pub struct CubeAnimation {
+    queue: VecDeque<Vec<MovingCube>>,
+}
+
+impl CubeAnimation {
+    pub fn pending(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.queue.clear();
+    }
+}
+
+pub fn animate(
+    resource: Res<CubesResource>,
+    board: Res<BoardResource>,
+    settings: Res<AnimationSettings>,
+    mut animation: ResMut<CubeAnimation>,
+    time: Res<Clock>,
+    mut events: EventReader<CubeSlid>,
+    mut query_bodies: Query<&mut Transform, With<CubeHome>>,
+) {
+    for event in events.iter() {
+        if let Some(entity) = board.entities.get(&event.home) {
+            let moving = MovingCube::new(*entity, &resource, event);
+            match animation.queue.back_mut() {
+                Some(group) if !event.lead => group.push(moving),
+                _ => animation.queue.push_back(vec![moving]),
+            }
+        }
+    }
+
+    let duration = settings.duration.max(f32::EPSILON);
+    let mut delta = time.delta_seconds();
+    while let Some(group) = animation.queue.front_mut() {
+        let mut elapsed = 0.0;
+        for moving in group.iter_mut() {
+            moving.elapsed += delta;
+            elapsed = moving.elapsed;
+            let t = moving.elapsed / duration;
+            if let Ok(mut tr) = query_bodies.get_mut(moving.entity) {
+                if t < 1.0 {
+                    moving.place(settings.easing.apply(t), &mut tr);
+                } else {
+                    moving.finish(&mut tr);
+                }
+            }
+        }
+
+        if elapsed < duration {
+            break;
+        }
+        // Hand the time left over from this move to the next one so queued moves run back to back.
+        delta = elapsed - duration;
+        animation.queue.pop_front();
+    }
+}
+
+pub fn unit_vec(d: Direction3D) -> Vec3 {
+    match d {
+        Direction3D::XNega => -Vec3::X,
+        Direction3D::XPosi => Vec3::X,
+        Direction3D::YNega => -Vec3::Y,
+        Direction3D::YPosi => Vec3::Y,
+        Direction3D::ZNega => -Vec3::Z,
+        Direction3D::ZPosi => Vec3::Z,
+    }
+}
+
+pub fn cycle_easing(keys: Res<Input<KeyCode>>, mut settings: ResMut<AnimationSettings>) {
+    if keys.just_pressed(KeyCode::E) {
+        settings.easing = settings.easing.next();
+        info!("Easing: {:?}", settings.easing);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::events::Step;
+    use crate::harness::Harness;
+    use alignment::board::Board;
+    use alignment::model::{Pos3D, Size3D};
+
+    #[test]
+    fn slides_are_drawn_to_the_end() {
+        let mut harness = Harness::manual();
+        let mv = harness.board().board.moves()[0];
+        let home = CubeHome(harness.board().board.get(mv.src).unwrap().home());
+        let to = harness.board().hole().unwrap();
+        let resource = harness.resource::<CubesResource>();
+        let (start, end) = (resource.calc_center(mv.src), resource.calc_center(to));
+
+        assert!(harness.slide(mv));
+        harness.advance(0.0);
+        let (pos, tr) = harness.body(home);
+        assert_eq!(pos, to);
+        assert_eq!(tr.translation, start);
+
+        harness.advance(0.5);
+        let (_, tr) = harness.body(home);
+        assert!(tr.translation.distance(start) > 0.1);
+        assert!(tr.translation.distance(end) > 0.1);
+        assert_eq!(harness.resource::<CubeAnimation>().pending(), 1);
+
+        harness.advance(0.6);
+        let (_, tr) = harness.body(home);
+        assert_eq!(tr.translation, end);
+        assert_eq!(tr.rotation, Quat::IDENTITY);
+        assert_eq!(harness.resource::<CubeAnimation>().pending(), 0);
+    }
+
+    #[test]
+    fn slides_onto_another_face_swing_around_the_edge() {
+        let moving = |size: Size3D, from: Pos3D, hole: Pos3D| {
+            let resource = CubesResource {
+                spaces: size,
+                cube_size: 1.0,
+                goal: Default::default(),
+                puzzle: Default::default(),
+            };
+            let board = Board::with_hole(size, hole);
+            let mv = *board.moves().iter().find(|mv| mv.src == from).unwrap();
+            let event = CubeSlid {
+                home: from,
+                from,
+                to: hole,
+                direction: mv.direction,
+                step: Step::Move(mv),
+                lead: true,
+            };
+            (MovingCube::new(Entity::from_raw(0), &resource, &event), resource)
+        };
+        let mut tr = Transform::default();
+
+        // Along the bottom front edge off the bottom face, bulging out of the front.
+        let (corner, resource) = moving(
+            Size3D::new(3, 3, 3),
+            Pos3D::new(1, 0, 0),
+            Pos3D::new(1, 1, 0),
+        );
+        let arc = corner.arc.as_ref().unwrap();
+        assert!((arc.axis.length() - 1.0).abs() < 1e-5);
+        corner.place(1.0, &mut tr);
+        assert!(tr.translation.distance(corner.end) < 1e-5);
+        corner.place(0.5, &mut tr);
+        assert!(tr.translation.z < corner.start.lerp(corner.end, 0.5).z);
+        assert!((tr.rotation.length() - 1.0).abs() < 1e-5);
+        let middle = resource.calc_center(Pos3D::new(1, 1, 1));
+        assert!(tr.translation.distance(middle) > corner.end.distance(middle));
+
+        // Across an axis two cells long, from one face straight onto the opposite one.
+        let size = Size3D::new(3, 3, 2);
+        let (across, _) = moving(size, Pos3D::new(1, 1, 0), Pos3D::new(1, 1, 1));
+        assert!(across.arc.is_none());
+        across.place(0.5, &mut tr);
+        assert_eq!(tr.translation, across.start.lerp(across.end, 0.5));
+        let (edge, _) = moving(size, Pos3D::new(0, 1, 0), Pos3D::new(0, 1, 1));
+        let arc = edge.arc.as_ref().unwrap();
+        assert!((arc.axis.length() - 1.0).abs() < 1e-5);
+        edge.place(1.0, &mut tr);
+        assert!(tr.translation.distance(edge.end) < 1e-5);
+
+        // Within a face, between two cells on no other face.
+        let (flat, _) = moving(
+            Size3D::new(4, 4, 4),
+            Pos3D::new(0, 1, 1),
+            Pos3D::new(0, 2, 1),
+        );
+        assert!(flat.arc.is_none());
+    }
+
+    #[test]
+    fn easing_keeps_ends() {
+        let mut easing = Easing::Linear;
+        loop {
+            assert_eq!(easing.apply(0.0), 0.0);
+            assert!((easing.apply(1.0) - 1.0).abs() < 1e-6);
+            easing = easing.next();
+            if easing == Easing::Linear {
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use crate::animation::CubeAnimation;
+use crate::events::CubeSlid;
+use crate::play::PlayMode;
+use crate::resources::{BoardResource, CubesResource};
+use alignment::belt::{Belt, Turn};
+use alignment::board::Board;
+use alignment::goal::Axis;
+use alignment::model::Pos3D;
+use bevy::prelude::*;
+use rand::prelude::*;
+
+/// Which puzzle the box plays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Puzzle {
+    /// Cubes slide into the hole.
+    #[default]
+    Slide,
+    /// Whole belts of cubes turn around the box, which has no hole at all.
+    Belts,
+}
+
+impl Puzzle {
+    pub fn name(self) -> &'static str {
+        match self {
+            Puzzle::Slide => "slide",
+            Puzzle::Belts => "belts",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        [Puzzle::Slide, Puzzle::Belts]
+            .into_iter()
+            .find(|puzzle| puzzle.name() == name)
+    }
+
+    #[must_use]
+    pub fn next(self) -> Self {
+        match self {
+            Puzzle::Slide => Puzzle::Belts,
+            Puzzle::Belts => Puzzle::Slide,
+        }
+    }
+}
+
+/// The axis a click turns belts around.
+pub struct BeltControl {
+    pub axis: Axis,
+}
+
+impl Default for BeltControl {
+    fn default() -> Self {
+        BeltControl { axis: Axis::Z }
+    }
+}
+
+/// Steps the axis clicks turn belts around with A.
+pub fn cycle_axis(keys: Res<Input<KeyCode>>, mut control: ResMut<BeltControl>) {
+    if keys.just_pressed(KeyCode::A) {
+        control.axis = match control.axis {
+            Axis::X => Axis::Y,
+            Axis::Y => Axis::Z,
+            Axis::Z => Axis::X,
+        };
+        info!("Belts turn around {}", control.axis.name());
+    }
+}
+
+/// The turn of the belt around `axis` through the cube at `pos`, forward unless `back`; nothing
+/// when the cell lies on no such belt or the belt is held by an obstacle.
+pub fn turn_at(board: &Board, pos: Pos3D, axis: Axis, back: bool) -> Option<Turn> {
+    let layer = match axis {
+        Axis::X => pos.x(),
+        Axis::Y => pos.y(),
+        Axis::Z => pos.z(),
+    };
+    let turn = Turn::new(Belt::new(axis, layer), !back);
+    board
+        .turns()
+        .into_iter()
+        .find(|t| *t == turn && t.belt.cells(board.size()).contains(&pos))
+}
+
+/// Turns a random belt whenever the demo runs short of moves to draw, never undoing the turn
+/// before.
+pub fn spin(
+    mode: Res<PlayMode>,
+    resource: Res<CubesResource>,
+    animation: Res<CubeAnimation>,
+    mut board: ResMut<BoardResource>,
+    mut undo: Local<Option<Turn>>,
+    mut events: EventWriter<CubeSlid>,
+) {
+    if *mode != PlayMode::Demo || resource.puzzle != Puzzle::Belts || animation.pending() > 1 {
+        return;
+    }
+    if resource.is_changed() {
+        return;
+    }
+    let board = &mut *board;
+    let turns: Vec<Turn> = board
+        .board
+        .turns()
+        .into_iter()
+        .filter(|turn| Some(*turn) != *undo)
+        .collect();
+    let turn = match turns.choose(&mut board.rng) {
+        Some(turn) => *turn,
+        None => return,
+    };
+    board
+        .turn(turn)
+        .into_iter()
+        .for_each(|slid| events.send(slid));
+    *undo = Some(turn.invert());
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::ViewerConfig;
+    use crate::harness::Harness;
+    use crate::hud::PlayStats;
+    use alignment::model::generate_surfaces;
+
+    fn belts() -> ViewerConfig {
+        ViewerConfig {
+            puzzle: Puzzle::Belts,
+            ..ViewerConfig::default()
+        }
+    }
+
+    #[test]
+    fn demo_turns_belts() {
+        let mut harness = Harness::new(belts(), PlayMode::Demo);
+        let spaces = harness.resource::<CubesResource>().spaces;
+        assert!(harness.board().board.holes().is_empty());
+        assert_eq!(harness.bodies().len(), generate_surfaces(spaces).len());
+
+        harness.run_for(5.0, 0.1);
+        assert!(harness.resource::<PlayStats>().moves >= 4);
+        assert!(harness.board().board.holes().is_empty());
+        // Every body is the one the board holds for its cube.
+        assert_eq!(harness.bodies().len(), generate_surfaces(spaces).len());
+    }
+
+    #[test]
+    fn turns_are_drawn_together() {
+        let mut harness = Harness::new(belts(), PlayMode::Manual);
+        harness.advance(0.1);
+        let board = harness.board().board.clone();
+        let pos = Pos3D::new(1, 0, 2);
+        assert_eq!(turn_at(&board, pos, Axis::Y, false), None);
+        let turn = turn_at(&board, pos, Axis::Z, true).unwrap();
+        assert_eq!(turn, Turn::new(Belt::new(Axis::Z, 2), false));
+
+        assert!(harness.turn(turn));
+        assert_eq!(
+            harness.board().board.at_home(),
+            board.parts().len() - turn.belt.cells(board.size()).len()
+        );
+        harness.advance(0.1);
+        assert_eq!(harness.resource::<CubeAnimation>().pending(), 1);
+        assert_eq!(harness.resource::<PlayStats>().moves, 1);
+
+        harness.run_for(1.0, 0.25);
+        assert_eq!(harness.resource::<CubeAnimation>().pending(), 0);
+        let centers: Vec<(Pos3D, Vec3)> = harness
+            .bodies()
+            .into_iter()
+            .map(|(_, pos, tr)| (pos, tr.translation))
+            .collect();
+        let resource = harness.resource::<CubesResource>();
+        centers
+            .into_iter()
+            .for_each(|(pos, center)| assert_eq!(center, resource.calc_center(pos)));
+    }
+}
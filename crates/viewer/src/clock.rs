@@ -0,0 +1,38 @@
+use bevy::prelude::*;
+use std::time::Duration;
+
+/// The time the puzzle's systems run on, copied from bevy's `Time` at the start of every frame.
+///
+/// Whatever owns the app can drive it by hand instead, ordered after `ViewerSystem::Tick`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Clock {
+    delta: Duration,
+    since_startup: Duration,
+}
+
+impl Clock {
+    /// Moves the clock on by `delta`.
+    pub fn advance(&mut self, delta: Duration) {
+        self.delta = delta;
+        self.since_startup += delta;
+    }
+
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    pub fn delta_seconds_f64(&self) -> f64 {
+        self.delta.as_secs_f64()
+    }
+
+    pub fn seconds_since_startup(&self) -> f64 {
+        self.since_startup.as_secs_f64()
+    }
+}
+
+pub fn tick(time: Res<Time>, mut clock: ResMut<Clock>) {
+    *clock = Clock {
+        delta: time.delta(),
+        since_startup: time.time_since_startup(),
+    };
+}
@@ -4,11 +4,22 @@ use bevy::prelude::*;
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Component)]
 pub struct CubeHome(pub Pos3D);
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Component)]
-pub struct CubePos(pub Pos3D);
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Component)]
+pub struct CubeFace(pub Direction3D);
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Component)]
-pub struct CubeHole(pub Pos3D);
+/// Marks the body of a cube that is pinned in place.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Component)]
+pub struct Pinned;
 
+/// A blocked cell, drawn as a dark block with no faces.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Component)]
-pub struct CubeFace(pub Direction3D);
+pub struct Wall(pub Pos3D);
+
+/// Marks the face plane drawn on the inner side of a face.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Component)]
+pub struct ReversedFace;
+
+/// The camera cubes are picked through; the puzzle's own camera carries it, and a host that keeps
+/// the camera for itself adds it to its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Component)]
+pub struct PuzzleCamera;
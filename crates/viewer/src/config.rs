@@ -0,0 +1,295 @@
+use crate::belts::Puzzle;
+use crate::faces::FaceTheme;
+use alignment::goal::GoalShape;
+use alignment::model::Size3D;
+use bevy::prelude::*;
+use textures::palette::Palette;
+
+/// Everything about the viewer the user may choose, before startup or from the settings panel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ViewerConfig {
+    pub spaces: Size3D,
+    pub cube_size: f32,
+    /// What the board is solved toward; every cube at home when the shape does not fit the box.
+    pub goal: GoalShape,
+    /// Slides into the hole, or turns of belts on a box without one.
+    pub puzzle: Puzzle,
+    /// Slides per second.
+    pub speed: f32,
+    pub window_width: f32,
+    pub window_height: f32,
+    /// Six picture asset paths, one per face in `Direction3D` order.
+    pub face_pictures: Vec<String>,
+    /// One picture asset path holding all six faces.
+    pub cube_map: Option<String>,
+    /// What the faces show; pictures when any are given, otherwise rings.
+    pub theme: Option<FaceTheme>,
+    /// Face colours, a preset or six user colours.
+    pub palette: Palette,
+    /// Where the game in progress is kept between sessions: a file on native, a `localStorage`
+    /// key on wasm. `off` keeps nothing.
+    pub save: Option<String>,
+    /// A puzzle to play from its start at startup, such as a maze of walls and pinned cubes, as a
+    /// share code or the file or key it was exported to.
+    pub level: Option<String>,
+    /// A game to play back at startup, as a share code or the file or key it was exported to.
+    pub replay: Option<String>,
+    /// How many times faster than it was played a replay runs.
+    pub replay_speed: f64,
+}
+
+impl Default for ViewerConfig {
+    fn default() -> Self {
+        ViewerConfig {
+            spaces: Size3D::new(3, 4, 5),
+            cube_size: 1.0,
+            goal: GoalShape::Identity,
+            puzzle: Puzzle::Slide,
+            speed: 1.0,
+            window_width: 800.0,
+            window_height: 800.0,
+            face_pictures: vec![],
+            cube_map: None,
+            theme: None,
+            palette: Palette::default(),
+            save: Some("slide-puzzle.save".to_string()),
+            level: None,
+            replay: None,
+            replay_speed: 1.0,
+        }
+    }
+}
+
+impl ViewerConfig {
+    pub const MIN_SPACE: u8 = 2;
+    pub const MAX_SPACE: u8 = 12;
+    pub const MIN_CUBE_SIZE: f32 = 0.25;
+    pub const MAX_CUBE_SIZE: f32 = 4.0;
+    pub const MIN_SPEED: f32 = 0.25;
+    pub const MAX_SPEED: f32 = 20.0;
+    /// Smallest side of the window, in logical pixels.
+    pub const MIN_WINDOW: f32 = 200.0;
+    pub const MIN_REPLAY_SPEED: f64 = 0.25;
+    pub const MAX_REPLAY_SPEED: f64 = 16.0;
+
+    /// Reads `--key value` arguments on native and `?key=value` URL parameters on wasm.
+    pub fn from_env() -> Self {
+        let mut config = ViewerConfig::default();
+        env_pairs().into_iter().for_each(|(key, value)| {
+            if !config.apply(&key, &value) {
+                warn!("Ignoring option {}={}", key, value);
+            }
+        });
+        config
+    }
+
+    /// Sets one option from its textual form, returning whether it was understood.
+    pub fn apply(&mut self, key: &str, value: &str) -> bool {
+        match key.replace('_', "-").as_str() {
+            "size" => parse_triple(value)
+                .map(|(x, y, z)| self.spaces = clamp_spaces(Size3D::new(x, y, z)))
+                .is_some(),
+            "cube-size" => value
+                .parse::<f32>()
+                .map(|v| self.cube_size = v.clamp(Self::MIN_CUBE_SIZE, Self::MAX_CUBE_SIZE))
+                .is_ok(),
+            "goal" => GoalShape::parse(value)
+                .map(|goal| self.goal = goal)
+                .is_some(),
+            "puzzle" => Puzzle::parse(value)
+                .map(|puzzle| self.puzzle = puzzle)
+                .is_some(),
+            "speed" => value
+                .parse::<f32>()
+                .map(|v| self.speed = v.clamp(Self::MIN_SPEED, Self::MAX_SPEED))
+                .is_ok(),
+            "window" => parse_pair(value)
+                .filter(|(w, h)| w.is_finite() && h.is_finite())
+                .map(|(w, h)| {
+                    self.window_width = w.max(Self::MIN_WINDOW);
+                    self.window_height = h.max(Self::MIN_WINDOW);
+                })
+                .is_some(),
+            "faces" => {
+                self.face_pictures = value.split(',').map(|v| v.trim().to_string()).collect();
+                self.face_pictures.len() == 6
+            }
+            "cube-map" => {
+                self.cube_map = Some(value.to_string());
+                true
+            }
+            "theme" => FaceTheme::parse(value)
+                .map(|theme| self.theme = Some(theme))
+                .is_some(),
+            "palette" => Palette::parse(value)
+                .map(|palette| {
+                    self.palette = Palette {
+                        cues: self.palette.cues || palette.cues,
+                        ..palette
+                    }
+                })
+                .is_some(),
+            "save" => match value {
+                "" => false,
+                "off" => {
+                    self.save = None;
+                    true
+                }
+                _ => {
+                    self.save = Some(value.to_string());
+                    true
+                }
+            },
+            "level" => match value {
+                "" => false,
+                _ => {
+                    self.level = Some(value.to_string());
+                    true
+                }
+            },
+            "replay" => match value {
+                "" => false,
+                _ => {
+                    self.replay = Some(value.to_string());
+                    true
+                }
+            },
+            "replay-speed" => value
+                .parse::<f64>()
+                .map(|v| {
+                    self.replay_speed = v.clamp(Self::MIN_REPLAY_SPEED, Self::MAX_REPLAY_SPEED)
+                })
+                .is_ok(),
+            "cues" => parse_flag(value)
+                .map(|cues| self.palette.cues = cues)
+                .is_some(),
+            _ => false,
+        }
+    }
+
+    pub fn face_theme(&self) -> FaceTheme {
+        self.theme.unwrap_or(
+            if self.cube_map.is_some() || !self.face_pictures.is_empty() {
+                FaceTheme::Picture
+            } else {
+                FaceTheme::Rings
+            },
+        )
+    }
+
+    pub fn window(&self) -> WindowDescriptor {
+        WindowDescriptor {
+            title: "Slide puzzle".to_string(),
+            width: self.window_width,
+            height: self.window_height,
+            ..Default::default()
+        }
+    }
+}
+
+pub fn clamp_spaces(size: Size3D) -> Size3D {
+    let clamp = |v: u8| v.clamp(ViewerConfig::MIN_SPACE, ViewerConfig::MAX_SPACE);
+    Size3D::new(clamp(size.x()), clamp(size.y()), clamp(size.z()))
+}
+
+fn parse_triple(value: &str) -> Option<(u8, u8, u8)> {
+    let vs: Vec<u8> = value
+        .split(['x', ','])
+        .map(|v| v.trim().parse().ok())
+        .collect::<Option<_>>()?;
+    match vs[..] {
+        [x, y, z] => Some((x, y, z)),
+        [n] => Some((n, n, n)),
+        _ => None,
+    }
+}
+
+fn parse_flag(value: &str) -> Option<bool> {
+    match value {
+        "" | "on" | "true" | "1" => Some(true),
+        "off" | "false" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+fn parse_pair(value: &str) -> Option<(f32, f32)> {
+    let (w, h) = value.split_once('x')?;
+    Some((w.trim().parse().ok()?, h.trim().parse().ok()?))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn env_pairs() -> Vec<(String, String)> {
+    arg_pairs(std::env::args().skip(1))
+}
+
+/// Pairs up `--key value` and `--key=value` arguments; a bare `--key` followed by another option
+/// or by nothing has an empty value.
+#[cfg(not(target_arch = "wasm32"))]
+fn arg_pairs(args: impl IntoIterator<Item = String>) -> Vec<(String, String)> {
+    let mut pairs = vec![];
+    let mut args = args.into_iter().peekable();
+    while let Some(arg) = args.next() {
+        if let Some(key) = arg.strip_prefix("--") {
+            let pair = match key.split_once('=') {
+                Some((key, value)) => (key.to_string(), value.to_string()),
+                None => {
+                    let value = args.next_if(|next| !next.starts_with("--"));
+                    (key.to_string(), value.unwrap_or_default())
+                }
+            };
+            pairs.push(pair);
+        } else {
+            warn!("Ignoring argument {}", arg);
+        }
+    }
+    pairs
+}
+
+#[cfg(target_arch = "wasm32")]
+fn env_pairs() -> Vec<(String, String)> {
+    let search = web_sys::window()
+        .and_then(|window| window.location().search().ok())
+        .unwrap_or_default();
+    search
+        .trim_start_matches('?')
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((key, value)) => (key.to_string(), value.to_string()),
+            None => (pair.to_string(), String::new()),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn bare_flags_leave_the_next_option_alone() {
+        let args = ["--hud", "--size", "5", "--cues", "--save=off", "stray"];
+        let pairs = arg_pairs(args.into_iter().map(String::from));
+        let pair = |key: &str, value: &str| (key.to_string(), value.to_string());
+        assert_eq!(
+            pairs,
+            vec![
+                pair("hud", ""),
+                pair("size", "5"),
+                pair("cues", ""),
+                pair("save", "off"),
+            ]
+        );
+    }
+
+    #[test]
+    fn windows_keep_a_usable_size() {
+        let mut config = ViewerConfig::default();
+        assert!(config.apply("window", "0x-40"));
+        assert_eq!(config.window_width, ViewerConfig::MIN_WINDOW);
+        assert_eq!(config.window_height, ViewerConfig::MIN_WINDOW);
+        assert!(!config.apply("window", "NaNx600"));
+        assert!(!config.apply("window", "infx600"));
+        assert_eq!(config.window_width, ViewerConfig::MIN_WINDOW);
+    }
+}
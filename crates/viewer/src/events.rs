@@ -0,0 +1,34 @@
+use alignment::belt::Turn;
+use alignment::board::Move;
+use alignment::model::*;
+
+/// What moved a cube: a move toward the hole, or the turn of a belt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    Move(Move),
+    Turn(Turn),
+}
+
+/// Sent whenever a cube moves on the board held by `BoardResource`.
+///
+/// A move shifting a line of cubes or turning a belt sends one event per cube, the first of them
+/// the lead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CubeSlid {
+    pub home: Pos3D,
+    pub from: Pos3D,
+    pub to: Pos3D,
+    pub direction: Direction3D,
+    pub step: Step,
+    /// Whether this is the first cube of its move, which stands for the move as a whole.
+    pub lead: bool,
+}
+
+/// Sent when a move or a belt turn leaves the board meeting the active goal.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PuzzleSolved {
+    /// Moves and turns since the game started.
+    pub moves: u32,
+    /// Seconds since the first manual slide, or zero while the demo runs.
+    pub seconds: f64,
+}
@@ -0,0 +1,247 @@
+use crate::config::ViewerConfig;
+use crate::resources::CubeAssets;
+use alignment::model::*;
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use textures::atlas::Atlas;
+use textures::palette::Palette;
+use textures::pattern::{slice, FaceSpec, Pattern, Tile};
+use textures::texture::{Picture, Texture};
+
+/// Pixels along each side of one face tile, before the atlas shrinks them to fit.
+pub const FACE_PIXELS: u32 = 100;
+
+/// Longest side of the face atlas, kept within what WebGL2 devices commonly accept.
+pub const MAX_ATLAS_PIXELS: u32 = 4096;
+
+/// What the face planes show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaceTheme {
+    Rings,
+    Stripes,
+    Gradient,
+    Checker,
+    /// The configured pictures, or rings when there are none.
+    Picture,
+    /// The home cube's position in the surface ordering.
+    HomeIndex,
+    /// Row and column of the home cube on this face.
+    RowColumn,
+}
+
+impl FaceTheme {
+    const ALL: [FaceTheme; 7] = [
+        FaceTheme::Rings,
+        FaceTheme::Stripes,
+        FaceTheme::Gradient,
+        FaceTheme::Checker,
+        FaceTheme::Picture,
+        FaceTheme::HomeIndex,
+        FaceTheme::RowColumn,
+    ];
+
+    fn index(self) -> usize {
+        FaceTheme::ALL
+            .iter()
+            .position(|t| *t == self)
+            .unwrap_or_default()
+    }
+
+    #[must_use]
+    pub fn next(self) -> Self {
+        FaceTheme::ALL[(self.index() + 1) % FaceTheme::ALL.len()]
+    }
+
+    #[must_use]
+    pub fn prev(self) -> Self {
+        FaceTheme::ALL[(self.index() + FaceTheme::ALL.len() - 1) % FaceTheme::ALL.len()]
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "rings" => Some(FaceTheme::Rings),
+            "stripes" => Some(FaceTheme::Stripes),
+            "gradient" => Some(FaceTheme::Gradient),
+            "checker" => Some(FaceTheme::Checker),
+            "picture" => Some(FaceTheme::Picture),
+            "index" => Some(FaceTheme::HomeIndex),
+            "grid" => Some(FaceTheme::RowColumn),
+            _ => None,
+        }
+    }
+
+    /// The generated pattern drawn for this theme, rings standing in for missing pictures.
+    fn pattern(self) -> Pattern {
+        match self {
+            FaceTheme::Rings | FaceTheme::Picture => Pattern::Rings,
+            FaceTheme::Stripes => Pattern::Stripes,
+            FaceTheme::Gradient => Pattern::Gradient,
+            FaceTheme::Checker => Pattern::Checker,
+            FaceTheme::HomeIndex => Pattern::HomeIndex,
+            FaceTheme::RowColumn => Pattern::RowColumn,
+        }
+    }
+}
+
+/// Pictures to slice across the faces.
+pub enum FaceSource {
+    /// One picture per face, in `Direction3D` order.
+    Pictures(Vec<Handle<Image>>),
+    /// One picture holding every face as a 3x2 grid of tiles, in `Direction3D` order row by row.
+    CubeMap(Handle<Image>),
+}
+
+pub struct FaceArt {
+    pub theme: FaceTheme,
+    pub palette: Palette,
+    pub source: Option<FaceSource>,
+}
+
+impl FromWorld for FaceArt {
+    fn from_world(world: &mut World) -> Self {
+        let config = world
+            .get_resource_or_insert_with(ViewerConfig::default)
+            .clone();
+        let asset_server = world
+            .get_resource::<AssetServer>()
+            .expect("AssetServer must be initialized first");
+        let source = match (&config.cube_map, config.face_pictures.len()) {
+            (Some(path), _) => Some(FaceSource::CubeMap(asset_server.load(path.as_str()))),
+            (None, 6) => Some(FaceSource::Pictures(
+                config
+                    .face_pictures
+                    .iter()
+                    .map(|path| asset_server.load(path.as_str()))
+                    .collect(),
+            )),
+            (None, 0) => None,
+            (None, n) => {
+                warn!("Expected 6 face pictures but got {}, drawing rings", n);
+                None
+            }
+        };
+        FaceArt {
+            theme: config.face_theme(),
+            palette: Palette::default(),
+            source,
+        }
+    }
+}
+
+impl FaceArt {
+    pub fn is_loaded(&self, images: &Assets<Image>) -> bool {
+        match &self.source {
+            None => true,
+            Some(FaceSource::Pictures(handles)) => handles.iter().all(|h| images.get(h).is_some()),
+            Some(FaceSource::CubeMap(handle)) => images.get(handle).is_some(),
+        }
+    }
+
+    fn picture<'a>(
+        &self,
+        images: &'a Assets<Image>,
+        direction: Direction3D,
+    ) -> Option<(Picture<'a>, Tile)> {
+        let index = direction as usize;
+        match self.source.as_ref()? {
+            FaceSource::Pictures(handles) => {
+                let picture = images.get(&handles[index]).and_then(as_picture)?;
+                let tile = Tile::whole(&picture);
+                Some((picture, tile))
+            }
+            FaceSource::CubeMap(handle) => {
+                let picture = images.get(handle).and_then(as_picture)?;
+                let tile = Tile::grid(&picture, 3, 2, index as u32);
+                Some((picture, tile))
+            }
+        }
+    }
+
+    /// The texture for one side of one face plane, falling back to rings until pictures load.
+    fn tile(&self, images: &Assets<Image>, texels: u32, spec: FaceSpec) -> Texture {
+        let picture = match self.theme {
+            FaceTheme::Picture => self.picture(images, spec.direction),
+            _ => None,
+        };
+        match picture {
+            Some((picture, tile)) => slice(texels, spec, &picture, tile),
+            None => self.theme.pattern().render(texels, &self.palette, spec),
+        }
+    }
+
+    /// Draws `planes` into the atlas, one tile each in the same order.
+    pub fn paint(&self, images: &Assets<Image>, atlas: &mut Atlas, planes: &[FaceSpec]) {
+        let texels = atlas.texels();
+        planes.iter().enumerate().for_each(|(i, spec)| {
+            atlas.put(i as u32, &self.tile(images, texels, *spec));
+        });
+    }
+
+    /// Wraps a painted atlas in an image; pictures are sRGB while generated patterns are not.
+    pub fn atlas_image(&self, images: &Assets<Image>, atlas: Atlas) -> Image {
+        let format = if self.theme == FaceTheme::Picture
+            && self.source.is_some()
+            && self.is_loaded(images)
+        {
+            TextureFormat::Rgba8UnormSrgb
+        } else {
+            TextureFormat::Rgba8Unorm
+        };
+        Image::new(
+            Extent3d {
+                width: atlas.width(),
+                height: atlas.height(),
+                ..Default::default()
+            },
+            TextureDimension::D2,
+            atlas.into_data(),
+            format,
+        )
+    }
+}
+
+/// An empty atlas with a slot for each of `count` face planes.
+pub fn face_atlas(count: usize) -> Atlas {
+    Atlas::new(count as u32, FACE_PIXELS, MAX_ATLAS_PIXELS)
+}
+
+/// Views a loaded image as plain RGBA bytes, if that is how it is stored.
+fn as_picture(image: &Image) -> Option<Picture<'_>> {
+    let size = image.texture_descriptor.size;
+    matches!(
+        image.texture_descriptor.format,
+        TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb
+    )
+    .then(|| Picture::new(size.width, size.height, &image.data))
+}
+
+/// Redraws the face atlas once the pictures have loaded or the art was swapped.
+pub fn retexture(
+    art: Res<FaceArt>,
+    assets: Res<CubeAssets>,
+    mut loaded: Local<bool>,
+    mut images: ResMut<Assets<Image>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let ready = art.is_loaded(&images);
+    let newly_loaded = ready && !*loaded;
+    *loaded = ready;
+    if !(art.is_changed() || newly_loaded) || assets.planes.is_empty() {
+        return;
+    }
+
+    let mut atlas = face_atlas(assets.planes.len());
+    art.paint(&images, &mut atlas, &assets.planes);
+    let image = art.atlas_image(&images, atlas);
+    // Swapping the handle re-prepares the one shared material with the new texture.
+    if let Some(material) = materials.get_mut(&assets.face_material) {
+        material.base_color_texture = Some(images.add(image));
+    }
+}
+
+/// Steps through the themes with T.
+pub fn cycle_theme(keys: Res<Input<KeyCode>>, mut config: ResMut<ViewerConfig>) {
+    if keys.just_pressed(KeyCode::T) {
+        config.theme = Some(config.face_theme().next());
+    }
+}
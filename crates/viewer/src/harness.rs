@@ -0,0 +1,182 @@
+//! Runs the puzzle's game logic without a window or a GPU, on a clock the test moves by hand.
+
+use crate::clock::Clock;
+use crate::components::*;
+use crate::config::ViewerConfig;
+use crate::events::CubeSlid;
+use crate::play::PlayMode;
+use crate::plugin::{SlidePuzzlePlugin, ViewerSystem};
+use crate::resources::BoardResource;
+use alignment::belt::Turn;
+use alignment::board::Move;
+use alignment::model::Pos3D;
+use bevy::app::Events;
+use bevy::asset::AssetPlugin;
+use bevy::input::keyboard::KeyboardInput;
+use bevy::input::{ElementState, InputPlugin};
+use bevy::prelude::*;
+use bevy::transform::TransformPlugin;
+use bevy::window::WindowPlugin;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// The time every system sees, advanced only by `Harness::advance`.
+struct ManualClock(Clock);
+
+/// Overwrites whatever the real clock said this frame with the manual one.
+fn sync_clock(manual: Res<ManualClock>, mut clock: ResMut<Clock>) {
+    *clock = manual.0;
+}
+
+pub struct Harness {
+    pub app: App,
+}
+
+impl Harness {
+    /// A puzzle in `mode` on a freshly built board, after its first frame.
+    ///
+    /// Nothing is saved or resumed, whatever `config` says.
+    pub fn new(config: ViewerConfig, mode: PlayMode) -> Self {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(TransformPlugin)
+            .add_plugin(InputPlugin)
+            .add_plugin(WindowPlugin::default())
+            .add_plugin(AssetPlugin)
+            .add_asset::<Mesh>()
+            .add_asset::<Image>()
+            .add_asset::<StandardMaterial>()
+            .insert_resource(ManualClock(Clock::default()))
+            .insert_resource(mode)
+            .add_system_to_stage(CoreStage::First, sync_clock.after(ViewerSystem::Tick))
+            .add_plugin(SlidePuzzlePlugin {
+                config: ViewerConfig {
+                    save: None,
+                    ..config
+                },
+                input: true,
+                camera: false,
+                ui: false,
+                lights: false,
+            });
+        app.update();
+        Harness { app }
+    }
+
+    pub fn manual() -> Self {
+        Harness::new(ViewerConfig::default(), PlayMode::Manual)
+    }
+
+    /// Moves the clock on by `seconds` and runs one frame.
+    pub fn advance(&mut self, seconds: f32) {
+        self.resource_mut::<ManualClock>()
+            .0
+            .advance(Duration::from_secs_f32(seconds));
+        self.app.update();
+    }
+
+    /// Runs frames `step` seconds apart until `seconds` have passed.
+    pub fn run_for(&mut self, seconds: f32, step: f32) {
+        let mut left = seconds;
+        while left > 0.0 {
+            self.advance(step.min(left));
+            left -= step;
+        }
+    }
+
+    /// Presses `key` for one frame and releases it on the next.
+    pub fn tap(&mut self, key: KeyCode) {
+        [ElementState::Pressed, ElementState::Released]
+            .into_iter()
+            .for_each(|state| {
+                self.app
+                    .world
+                    .get_resource_mut::<Events<KeyboardInput>>()
+                    .unwrap()
+                    .send(KeyboardInput {
+                        scan_code: 0,
+                        key_code: Some(key),
+                        state,
+                    });
+                self.advance(0.0);
+            });
+    }
+
+    /// Makes a move as a click would, without the window and camera a click needs.
+    pub fn slide(&mut self, mv: Move) -> bool {
+        let slid = self
+            .app
+            .world
+            .get_resource_mut::<BoardResource>()
+            .unwrap()
+            .shift(mv);
+        let made = !slid.is_empty();
+        let mut events = self
+            .app
+            .world
+            .get_resource_mut::<Events<CubeSlid>>()
+            .unwrap();
+        slid.into_iter().for_each(|slid| events.send(slid));
+        made
+    }
+
+    /// Turns a belt as a click would.
+    pub fn turn(&mut self, turn: Turn) -> bool {
+        let slid = self
+            .app
+            .world
+            .get_resource_mut::<BoardResource>()
+            .unwrap()
+            .turn(turn);
+        let made = !slid.is_empty();
+        let mut events = self
+            .app
+            .world
+            .get_resource_mut::<Events<CubeSlid>>()
+            .unwrap();
+        slid.into_iter().for_each(|slid| events.send(slid));
+        made
+    }
+
+    pub fn resource<T: Send + Sync + 'static>(&self) -> &T {
+        self.app.world.get_resource::<T>().unwrap()
+    }
+
+    pub fn resource_mut<T: Send + Sync + 'static>(&mut self) -> Mut<'_, T> {
+        self.app.world.get_resource_mut::<T>().unwrap()
+    }
+
+    pub fn board(&self) -> &BoardResource {
+        self.resource::<BoardResource>()
+    }
+
+    /// Where each body's cube stands on the board and where the body stands on screen, keyed by
+    /// its home; every body must be the one the board holds for its cube.
+    pub fn bodies(&mut self) -> Vec<(CubeHome, Pos3D, Transform)> {
+        let board = self.board();
+        let entities = board.entities.clone();
+        let cells: HashMap<Pos3D, Pos3D> = board
+            .board
+            .parts()
+            .iter()
+            .map(|(pos, cube)| (cube.home(), *pos))
+            .collect();
+        self.app
+            .world
+            .query::<(Entity, &CubeHome, &Transform)>()
+            .iter(&self.app.world)
+            .map(|(entity, home, tr)| {
+                assert_eq!(entities.get(&home.0), Some(&entity));
+                (*home, cells[&home.0], *tr)
+            })
+            .collect()
+    }
+
+    pub fn body(&mut self, home: CubeHome) -> (Pos3D, Transform) {
+        self.bodies()
+            .into_iter()
+            .find(|(h, _, _)| *h == home)
+            .map(|(_, pos, tr)| (pos, tr))
+            .unwrap()
+    }
+}
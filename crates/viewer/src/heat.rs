@@ -0,0 +1,164 @@
+use crate::components::*;
+use crate::events::CubeSlid;
+use crate::net::NetView;
+use crate::resources::{BoardResource, CubeAssets, CubesResource};
+use crate::ui::UiFont;
+use bevy::prelude::*;
+
+/// Swatches in the legend, spread evenly from home to the furthest a cube can be.
+const LEGEND_SWATCHES: usize = 8;
+
+/// Body opacity while tinted, a little above the plain bodies so the colours read.
+const HEAT_ALPHA: f32 = 0.55;
+
+/// Tints every body by how many slides it is from home, instead of the plain shared material.
+#[derive(Default)]
+pub struct HeatMap {
+    pub on: bool,
+    /// One material per distance, from 0 up to the diameter of the box.
+    materials: Vec<Handle<StandardMaterial>>,
+}
+
+#[derive(Debug, Clone, Copy, Component)]
+pub struct HeatLegend;
+
+#[derive(Debug, Clone, Copy, Component)]
+pub struct HeatLegendMax;
+
+/// Green at home, through yellow, to red at the far side of the box.
+fn ramp(t: f32) -> Color {
+    let t = t.clamp(0.0, 1.0);
+    if t < 0.5 {
+        Color::rgb(0.2 + 1.6 * t, 0.8, 0.2)
+    } else {
+        Color::rgb(1.0, 0.8 - 1.4 * (t - 0.5), 0.2)
+    }
+}
+
+pub fn setup(mut commands: Commands, font: Res<UiFont>) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Px(10.0),
+                    left: Val::Px(10.0),
+                    ..Default::default()
+                },
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Center,
+                padding: Rect::all(Val::Px(6.0)),
+                display: Display::None,
+                ..Default::default()
+            },
+            color: Color::rgba(0.0, 0.0, 0.0, 0.5).into(),
+            ..Default::default()
+        })
+        .insert(HeatLegend)
+        .with_children(|legend| {
+            legend.spawn_bundle(TextBundle {
+                text: Text::with_section("Home 0 ", font.style(18.0), Default::default()),
+                ..Default::default()
+            });
+            (0..LEGEND_SWATCHES).for_each(|i| {
+                legend.spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(18.0), Val::Px(18.0)),
+                        ..Default::default()
+                    },
+                    color: ramp(i as f32 / (LEGEND_SWATCHES - 1) as f32).into(),
+                    ..Default::default()
+                });
+            });
+            legend
+                .spawn_bundle(TextBundle {
+                    text: Text::with_section("", font.style(18.0), Default::default()),
+                    ..Default::default()
+                })
+                .insert(HeatLegendMax);
+        });
+}
+
+/// Turns the heat map on and off with M.
+pub fn toggle(
+    keys: Res<Input<KeyCode>>,
+    mut heat: ResMut<HeatMap>,
+    mut query: Query<&mut Style, With<HeatLegend>>,
+) {
+    if keys.just_pressed(KeyCode::M) {
+        heat.on = !heat.on;
+        for mut style in query.iter_mut() {
+            style.display = if heat.on {
+                Display::Flex
+            } else {
+                Display::None
+            };
+        }
+    }
+}
+
+/// Re-tints the bodies whenever a cube moves, the board is rebuilt or the map is switched; pinned
+/// cubes keep the obstacle material.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn tint(
+    resource: Res<CubesResource>,
+    board: Res<BoardResource>,
+    assets: Res<CubeAssets>,
+    net: Res<NetView>,
+    mut heat: ResMut<HeatMap>,
+    mut events: EventReader<CubeSlid>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut query_bodies: Query<
+        (ChangeTrackers<CubeHome>, &mut Handle<StandardMaterial>),
+        Without<Pinned>,
+    >,
+    mut query_max: Query<&mut Text, With<HeatLegendMax>>,
+) {
+    let moved = events.iter().count() > 0;
+    if resource.is_changed() || heat.materials.is_empty() {
+        let diameter = board.distances.diameter();
+        heat.materials = (0..=diameter)
+            .map(|d| {
+                let mut material: StandardMaterial = ramp(d as f32 / diameter.max(1) as f32).into();
+                material.base_color.set_a(HEAT_ALPHA * (1.0 - net.progress));
+                material.alpha_mode = AlphaMode::Blend;
+                materials.add(material)
+            })
+            .collect();
+        for mut text in query_max.iter_mut() {
+            text.sections[0].value = format!(" {} far", diameter);
+        }
+    }
+    if net.is_changed() {
+        // Fade with the plain bodies while the net opens.
+        heat.materials.iter().for_each(|handle| {
+            if let Some(material) = materials.get_mut(handle) {
+                material.base_color.set_a(HEAT_ALPHA * (1.0 - net.progress));
+            }
+        });
+    }
+    // Freshly built bodies come with the plain material and need tinting on their first frame.
+    let all = moved || heat.is_changed();
+    for (pos, cube) in board.board.parts() {
+        let body = board
+            .entities
+            .get(&cube.home())
+            .and_then(|entity| query_bodies.get_mut(*entity).ok());
+        let (tracker, mut material) = match body {
+            Some(body) => body,
+            None => continue,
+        };
+        if !(all || tracker.is_added()) {
+            continue;
+        }
+        *material = if heat.on {
+            let steps = board
+                .goal
+                .distance(&board.distances, *pos, *cube)
+                .unwrap_or_default();
+            heat.materials[(steps as usize).min(heat.materials.len() - 1)].clone()
+        } else {
+            assets.body_material.clone()
+        };
+    }
+}
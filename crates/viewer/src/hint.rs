@@ -0,0 +1,168 @@
+use crate::belts::Puzzle;
+use crate::clock::Clock;
+use crate::events::{CubeSlid, Step};
+use crate::resources::{BoardResource, CubesResource};
+use alignment::board::Move;
+use alignment::solver::Solver;
+use bevy::prelude::*;
+
+/// Boards the solver may expand for one hint; enough to see a few moves ahead on any box
+/// without stalling a frame for long.
+const HINT_BUDGET: usize = 20_000;
+
+/// Upcoming moves drawn as ghosts while the preview is on.
+const PREVIEW_MOVES: usize = 5;
+
+/// The moves the solver suggested, first one next, and whether to preview the ones after it.
+#[derive(Debug, Clone, Default)]
+pub struct Hint {
+    pub moves: Vec<Move>,
+    pub preview: bool,
+}
+
+/// Glowing shell around the cube the hint says to slide.
+#[derive(Debug, Clone, Copy, Component)]
+pub struct HintGlow;
+
+/// Translucent stand-in for where a previewed move will leave its cube.
+#[derive(Debug, Clone, Copy, Component)]
+pub struct HintGhost;
+
+fn plan(board: &BoardResource) -> Vec<Move> {
+    let plan = Solver::with_goal(&board.distances, &board.goal).solve(&board.board, HINT_BUDGET);
+    info!(
+        "Hint: {} moves after {} boards{}",
+        plan.moves.len(),
+        plan.expanded,
+        if plan.solved { ", solved" } else { "" }
+    );
+    plan.moves
+}
+
+/// Asks the solver for a hint with H, and toggles the ghost preview of later moves with G.
+///
+/// Hints are for the sliding puzzle; the belts are left to the player.
+pub fn request(
+    keys: Res<Input<KeyCode>>,
+    resource: Res<CubesResource>,
+    board: Res<BoardResource>,
+    mut hint: ResMut<Hint>,
+) {
+    if resource.puzzle != Puzzle::Slide {
+        return;
+    }
+    if keys.just_pressed(KeyCode::H) {
+        hint.moves = plan(&board);
+    }
+    if keys.just_pressed(KeyCode::G) {
+        hint.preview = !hint.preview;
+        if hint.preview && hint.moves.is_empty() {
+            hint.moves = plan(&board);
+        }
+    }
+}
+
+/// Keeps the rest of the plan while the player follows it, and drops it on any other change.
+pub fn follow(
+    resource: Res<CubesResource>,
+    mut events: EventReader<CubeSlid>,
+    mut hint: ResMut<Hint>,
+) {
+    if resource.is_changed() && !hint.moves.is_empty() {
+        hint.moves.clear();
+    }
+    for event in events.iter().filter(|slid| slid.lead) {
+        if hint.moves.is_empty() {
+            continue;
+        }
+        if event.step == Step::Move(hint.moves[0]) {
+            hint.moves.remove(0);
+        } else {
+            hint.moves.clear();
+        }
+    }
+}
+
+/// Redraws the glow and ghosts whenever the hint changes; the board itself is never touched.
+#[allow(clippy::type_complexity)]
+pub fn show(
+    mut commands: Commands,
+    hint: Res<Hint>,
+    resource: Res<CubesResource>,
+    board: Res<BoardResource>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    query: Query<Entity, Or<(With<HintGlow>, With<HintGhost>)>>,
+) {
+    if !hint.is_changed() {
+        return;
+    }
+    query.iter().for_each(|entity| {
+        commands.entity(entity).despawn_recursive();
+    });
+
+    let first = match hint.moves.first() {
+        Some(first) => first,
+        None => return,
+    };
+    let body = board
+        .board
+        .get(first.src)
+        .and_then(|cube| board.entities.get(&cube.home()));
+    if let Some(body) = body {
+        let mut glow: StandardMaterial = Color::rgba(1.0, 0.85, 0.2, 0.35).into();
+        glow.emissive = Color::rgb(1.0, 0.75, 0.1);
+        glow.alpha_mode = AlphaMode::Blend;
+        let glow = commands
+            .spawn_bundle(PbrBundle {
+                mesh: meshes.add(Mesh::from(shape::Cube {
+                    size: resource.cube_size * 1.05,
+                })),
+                material: materials.add(glow),
+                ..Default::default()
+            })
+            .insert(HintGlow)
+            .id();
+        commands.entity(*body).push_children(&[glow]);
+    }
+
+    if !hint.preview {
+        return;
+    }
+    // Play the plan forward on a copy to find where each previewed cube comes to rest.
+    let mut ahead = board.board.clone();
+    let ghost_mesh = meshes.add(Mesh::from(shape::Cube {
+        size: resource.cube_size * 0.9,
+    }));
+    hint.moves
+        .iter()
+        .take(PREVIEW_MOVES)
+        .enumerate()
+        .for_each(|(i, mv)| {
+            let to = ahead.destination(mv.src, mv.direction).map(|(_, to)| to);
+            if !ahead.apply(*mv) {
+                return;
+            }
+            if let Some(to) = to {
+                let fade = 1.0 - i as f32 / PREVIEW_MOVES as f32;
+                let mut ghost: StandardMaterial = Color::rgba(0.3, 0.8, 1.0, 0.5 * fade).into();
+                ghost.alpha_mode = AlphaMode::Blend;
+                commands
+                    .spawn_bundle(PbrBundle {
+                        mesh: ghost_mesh.clone(),
+                        material: materials.add(ghost),
+                        transform: Transform::from_translation(resource.calc_center(to)),
+                        ..Default::default()
+                    })
+                    .insert(HintGhost);
+            }
+        });
+}
+
+/// Makes the glow breathe so it catches the eye.
+pub fn pulse(time: Res<Clock>, mut query: Query<&mut Transform, With<HintGlow>>) {
+    let phase = (time.seconds_since_startup() as f32 * std::f32::consts::TAU * 1.5).sin();
+    for mut tr in query.iter_mut() {
+        tr.scale = Vec3::splat(1.0 + 0.04 * phase);
+    }
+}
@@ -0,0 +1,168 @@
+use crate::clock::Clock;
+use crate::events::{CubeSlid, PuzzleSolved};
+use crate::play::PlayMode;
+use crate::resources::{BoardResource, CubesResource};
+use crate::ui::UiFont;
+use bevy::prelude::*;
+
+/// Progress of the current game, recomputed from the board after every slide.
+#[derive(Debug, Clone, Default)]
+pub struct PlayStats {
+    pub moves: u32,
+    pub started: Option<f64>,
+    pub stopped: Option<f64>,
+    pub at_home: usize,
+    pub cubes: usize,
+    pub disorder: u32,
+}
+
+impl PlayStats {
+    /// Seconds since the first move, frozen once the board is solved.
+    pub fn elapsed(&self, now: f64) -> f64 {
+        match (self.started, self.stopped) {
+            (Some(started), Some(stopped)) => stopped - started,
+            (Some(started), None) => now - started,
+            _ => 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Component)]
+pub struct HudText;
+
+pub fn setup(mut commands: Commands, font: Res<UiFont>) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(10.0),
+                    left: Val::Px(10.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section("", font.style(20.0), Default::default()),
+            ..Default::default()
+        })
+        .insert(HudText);
+}
+
+pub fn track(
+    mode: Res<PlayMode>,
+    resource: Res<CubesResource>,
+    board: Res<BoardResource>,
+    time: Res<Clock>,
+    mut stats: ResMut<PlayStats>,
+    mut events: EventReader<CubeSlid>,
+    mut solved: EventWriter<PuzzleSolved>,
+) {
+    // A new board or a switch between demo and play starts a new game.
+    let restarted = resource.is_changed() || mode.is_changed();
+    if restarted {
+        *stats = PlayStats::default();
+    }
+
+    let moves = events.iter().filter(|slid| slid.lead).count() as u32;
+    if moves == 0 && !restarted {
+        return;
+    }
+
+    let now = time.seconds_since_startup();
+    stats.moves += moves;
+    stats.at_home = board.goal.satisfied(&board.board);
+    stats.cubes = board.board.parts().len();
+    stats.disorder = board.goal.disorder(&board.board, &board.distances);
+    if *mode == PlayMode::Manual && moves > 0 && stats.started.is_none() {
+        stats.started = Some(now);
+    }
+    if stats.started.is_some() && stats.stopped.is_none() && board.is_solved() {
+        stats.stopped = Some(now);
+    }
+    if moves > 0 && board.is_solved() {
+        solved.send(PuzzleSolved {
+            moves: stats.moves,
+            seconds: stats.elapsed(now),
+        });
+    }
+}
+
+pub fn show(
+    mode: Res<PlayMode>,
+    stats: Res<PlayStats>,
+    time: Res<Clock>,
+    mut query: Query<&mut Text, With<HudText>>,
+) {
+    let elapsed = stats.elapsed(time.seconds_since_startup());
+    let value = format!(
+        "Moves    {}\nTime     {:02}:{:04.1}\nIn place {}/{}\nDisorder {}\n{:?} (Space)",
+        stats.moves,
+        (elapsed / 60.0) as u32,
+        elapsed % 60.0,
+        stats.at_home,
+        stats.cubes,
+        stats.disorder,
+        *mode,
+    );
+    for mut text in query.iter_mut() {
+        text.sections[0].value = value.clone();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::ViewerConfig;
+    use crate::harness::Harness;
+    use alignment::board::Move;
+    use alignment::goal::{Axis, GoalShape};
+    use bevy::app::Events;
+
+    #[test]
+    fn solving_is_timed_and_announced() {
+        let mut harness = Harness::manual();
+        let cubes = harness.board().board.parts().len();
+        let hole = harness.board().hole().unwrap();
+        let mv = harness.board().board.moves()[0];
+
+        assert!(harness.slide(mv));
+        harness.advance(0.5);
+        let stats = harness.resource::<PlayStats>().clone();
+        assert_eq!(
+            (stats.moves, stats.at_home, stats.cubes),
+            (1, cubes - 1, cubes)
+        );
+        assert_eq!(stats.disorder, 1);
+        assert!(stats.started.is_some() && stats.stopped.is_none());
+
+        assert!(harness.slide(Move::new(hole, mv.direction.invert())));
+        harness.advance(1.5);
+        let stats = harness.resource::<PlayStats>().clone();
+        assert_eq!((stats.moves, stats.at_home, stats.disorder), (2, cubes, 0));
+        assert!((stats.elapsed(0.0) - 1.5).abs() < 1e-3);
+
+        let events = harness.resource::<Events<PuzzleSolved>>();
+        let solved: Vec<_> = events.get_reader().iter(events).copied().collect();
+        assert_eq!(solved.len(), 1);
+        assert_eq!(solved[0].moves, 2);
+    }
+
+    #[test]
+    fn goals_decide_what_is_in_place() {
+        let config = ViewerConfig {
+            goal: GoalShape::Mirror(Axis::X),
+            ..Default::default()
+        };
+        let mut harness = Harness::new(config, PlayMode::Manual);
+        let cubes = harness.board().board.parts().len();
+        assert!(harness.board().is_solved());
+        assert!(!harness.board().board.is_solved());
+
+        let mv = harness.board().board.moves()[0];
+        assert!(harness.slide(mv));
+        harness.advance(0.5);
+        let stats = harness.resource::<PlayStats>().clone();
+        assert_eq!((stats.at_home, stats.disorder), (cubes - 1, 1));
+        assert!(!harness.board().is_solved());
+    }
+}
@@ -1,8 +1,32 @@
-mod components;
+mod animation;
+mod belts;
+mod clock;
+pub mod components;
+pub mod config;
+pub mod events;
+mod faces;
+#[cfg(test)]
+mod harness;
+mod heat;
+mod hint;
+mod hud;
 mod move_cubes;
+mod net;
+mod play;
+mod plugin;
 mod put_cubes;
+mod replay;
 mod resources;
 mod rotate;
+mod save;
+mod settings;
 mod setup;
+mod ui;
+mod victory;
 
+pub use belts::Puzzle;
+pub use clock::Clock;
+pub use faces::FaceTheme;
+pub use play::PlayMode;
+pub use plugin::{SlidePuzzlePlugin, ViewerSystem};
 pub use setup::run;
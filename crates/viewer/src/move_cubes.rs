@@ -1,115 +1,104 @@
-use crate::components::*;
-use crate::resources::CubesResource;
+use crate::animation::CubeAnimation;
+use crate::belts::Puzzle;
+use crate::events::CubeSlid;
+use crate::play::PlayMode;
+use crate::resources::{BoardResource, CubesResource};
+use alignment::board::Board;
 use alignment::model::*;
 use alignment::slide::*;
 use bevy::prelude::*;
 use rand::prelude::*;
 use tinyvec::ArrayVec;
 
-#[derive(Debug, Clone, Component)]
-pub struct ShuffleTickTimer(pub Timer);
-
-pub struct MovingCube {
-    pub home: Pos3D,
-    pub prev_pos: Pos3D,
-    pub next_pos: Pos3D,
-    pub direction: Direction3D,
-    pub step: u8,
-}
-
-impl MovingCube {
-    const FASE_STEPS: u8 = 10;
-}
-
-impl Default for MovingCube {
-    fn default() -> Self {
-        Self {
-            home: Default::default(),
-            prev_pos: Default::default(),
-            next_pos: Default::default(),
-            direction: Direction3D::XNega,
-            step: 0,
-        }
-    }
-}
-
-pub fn setup(mut commands: Commands) {
-    commands
-        .spawn()
-        .insert(ShuffleTickTimer(Timer::from_seconds(0.1, true)));
-}
-
-pub fn action(
+pub fn shuffle(
+    mode: Res<PlayMode>,
     resource: Res<CubesResource>,
-    mut moving: Local<MovingCube>,
-    time: Res<Time>,
-    mut query_timer: Query<&mut ShuffleTickTimer>,
-    mut query_hole: Query<&mut CubeHole>,
-    mut query_bodies: Query<
-        (&CubeHome, &mut CubePos, &mut Transform, &mut Handle<Mesh>),
-        Without<CubeFace>,
-    >,
-    mut query_faces: Query<(
-        &CubeHome,
-        &mut CubePos,
-        &CubeFace,
-        &mut Transform,
-        &mut Handle<Mesh>,
-    )>,
+    mut board: ResMut<BoardResource>,
+    animation: Res<CubeAnimation>,
+    mut prev_direction: Local<Direction3D>,
+    mut events: EventWriter<CubeSlid>,
 ) {
-    let mut my_timer = query_timer.single_mut();
-    if my_timer.0.tick(time.delta()).just_finished() {
-        if moving.step >= MovingCube::FASE_STEPS {
-            moving.step = 0;
-        }
-
-        if moving.step == 0 {
-            let mut hole = query_hole.single_mut();
-            info!("Current hole: {:?}", hole);
-            let (next_hole, d) = shuffle_one(resource.spaces, hole.0, moving.direction);
-            moving.prev_pos = next_hole;
-            moving.next_pos = hole.0;
-            moving.direction = d;
-            moving.home = query_bodies
-                .iter()
-                .find(|(_, p, _, _)| p.0 == next_hole)
-                .map(|(h, _, _, _)| h.0)
-                .unwrap_or(next_hole);
-            hole.0 = next_hole;
-        }
-        moving.step += 1;
-
-        let a = resource.calc_center(moving.prev_pos);
-        let b = resource.calc_center(moving.next_pos);
-        let delta = (b - a) / (MovingCube::FASE_STEPS as f32);
-        info!(
-            "Move delta: {:?} = {:?}({:?}) - {:?}({:?})",
-            delta, b, moving.next_pos, a, moving.prev_pos
-        );
+    if *mode != PlayMode::Demo || resource.puzzle != Puzzle::Slide {
+        return;
+    }
+    // A board rebuilt this frame has no bodies to move until the frame's commands are applied.
+    if resource.is_changed() {
+        return;
+    }
+    // Keep one slide queued behind the one being drawn so the animation never waits for input.
+    if animation.pending() > 1 {
+        return;
+    }
 
-        for (home, mut pos, mut tr, _) in query_bodies.iter_mut() {
-            if home.0 == moving.home {
-                pos.0 = moving.next_pos;
-                tr.translation += delta;
-            }
-        }
-        for (home, mut pos, _, mut tr, _) in query_faces.iter_mut() {
-            if home.0 == moving.home {
-                pos.0 = moving.next_pos;
-                tr.translation += delta;
-            }
-        }
+    let hole = match board.hole() {
+        Some(hole) => hole,
+        None => return,
+    };
+    info!("Current hole: {:?}", hole);
+    let board = &mut *board;
+    let picked = shuffle_one(&mut board.rng, &board.board, hole, *prev_direction);
+    if let Some(slid) = picked.and_then(|(next_hole, d)| board.slide(next_hole, d)) {
+        events.send(slid);
+        *prev_direction = slid.direction;
     }
 }
 
-fn shuffle_one(size: Size3D, hole: Pos3D, prev_direction: Direction3D) -> (Pos3D, Direction3D) {
-    let mut rng = rand::thread_rng();
-
-    let ds: ArrayVec<[Direction3D; 3]> = adjacents(hole, size)
+/// A neighbour of the hole whose cube can slide into it, and the way it slides; turning back only
+/// when the hole is in a dead end between obstacles.
+fn shuffle_one<R: Rng>(
+    rng: &mut R,
+    board: &Board,
+    hole: Pos3D,
+    prev_direction: Direction3D,
+) -> Option<(Pos3D, Direction3D)> {
+    let size = board.size();
+    let ds: ArrayVec<[Direction3D; 4]> = adjacents(hole, size)
         .into_iter()
+        .filter(|d| {
+            move_one(hole, size, *d)
+                .and_then(|pos| board.destination(pos, d.invert()))
+                .is_some()
+        })
+        .collect();
+    let ahead: ArrayVec<[Direction3D; 4]> = ds
+        .iter()
+        .copied()
         .filter(|d| *d != prev_direction)
         .collect();
+    let ds = if ahead.is_empty() { ds } else { ahead };
+    if ds.is_empty() {
+        return None;
+    }
     let d = ds[rng.gen_range(0..ds.len())];
     let pos = move_one(hole, size, d).unwrap_or(hole);
-    (pos, d.invert())
+    Some((pos, d.invert()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::ViewerConfig;
+    use crate::harness::Harness;
+    use crate::hud::PlayStats;
+
+    #[test]
+    fn demo_shuffles_until_stopped() {
+        let mut harness = Harness::new(ViewerConfig::default(), PlayMode::Demo);
+        harness.run_for(5.0, 0.1);
+        assert!(harness.resource::<PlayStats>().moves >= 4);
+
+        harness.tap(KeyCode::Space);
+        assert_eq!(*harness.resource::<PlayMode>(), PlayMode::Manual);
+        let hole = harness.board().hole().unwrap();
+        harness.run_for(3.0, 0.1);
+        assert_eq!(harness.resource::<PlayStats>().moves, 0);
+        assert_eq!(harness.board().hole(), Some(hole));
+
+        // Every body has come to rest on the cell the board holds its cube in.
+        let bodies = harness.bodies();
+        let resource = harness.resource::<CubesResource>();
+        bodies
+            .into_iter()
+            .for_each(|(_, pos, tr)| assert_eq!(tr.translation, resource.calc_center(pos)));
+    }
 }
@@ -0,0 +1,119 @@
+use crate::clock::Clock;
+use crate::components::*;
+use crate::put_cubes::face_transform;
+use crate::resources::{CubeAssets, CubesResource};
+use crate::rotate::FaceFocus;
+use alignment::model::*;
+use bevy::prelude::*;
+use std::f32::consts::FRAC_PI_2;
+
+/// Whether the box is opened out into a flat cross, and how far along the opening is.
+///
+/// ZPosi stays put as the centre of the cross, XNega, XPosi, YPosi and YNega fold out around it,
+/// and ZNega hangs off the far side of XPosi.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NetView {
+    pub open: bool,
+    pub progress: f32,
+}
+
+impl NetView {
+    /// Seconds to open or close the net.
+    const DURATION: f32 = 0.8;
+}
+
+/// Lowest and highest corners of the box the cubes fill.
+fn extents(resource: &CubesResource) -> (Vec3, Vec3) {
+    let spaces = resource.spaces;
+    let far = Pos3D::new(spaces.x() - 1, spaces.y() - 1, spaces.z() - 1);
+    let half = Vec3::splat(resource.cube_size / 2.0);
+    (
+        resource.calc_center(Pos3D::new(0, 0, 0)) - half,
+        resource.calc_center(far) + half,
+    )
+}
+
+/// Turns by `angle` around the `axis` through `pivot`.
+fn hinge(pivot: Vec3, axis: Vec3, angle: f32) -> Transform {
+    Transform::from_translation(pivot)
+        * Transform::from_rotation(Quat::from_axis_angle(axis, angle))
+        * Transform::from_translation(-pivot)
+}
+
+/// Rigid motion carrying face `d` from its place on the box (`progress` 0) into the net (1).
+pub fn unfold(resource: &CubesResource, d: Direction3D, progress: f32) -> Transform {
+    let (lo, hi) = extents(resource);
+    let angle = FRAC_PI_2 * progress;
+    let x_posi = || hinge(Vec3::new(hi.x, 0.0, hi.z), Vec3::Y, -angle);
+    match d {
+        Direction3D::ZPosi => Transform::identity(),
+        Direction3D::XNega => hinge(Vec3::new(lo.x, 0.0, hi.z), Vec3::Y, angle),
+        Direction3D::XPosi => x_posi(),
+        Direction3D::YNega => hinge(Vec3::new(0.0, lo.y, hi.z), Vec3::X, -angle),
+        Direction3D::YPosi => hinge(Vec3::new(0.0, hi.y, hi.z), Vec3::X, angle),
+        Direction3D::ZNega => x_posi() * hinge(Vec3::new(hi.x, 0.0, lo.z), Vec3::Y, -angle),
+    }
+}
+
+/// Centre and radius of a circle holding the whole opened net.
+pub fn bounds(resource: &CubesResource) -> (Vec3, f32) {
+    let (lo, hi) = extents(resource);
+    let size = hi - lo;
+    let min = Vec3::new(lo.x - size.z, lo.y - size.z, hi.z);
+    let max = Vec3::new(hi.x + size.z + size.x, hi.y + size.z, hi.z);
+    ((min + max) / 2.0, (max - min).length() / 2.0)
+}
+
+/// Opens or closes the net with N, turning the camera to look down on it.
+pub fn toggle(keys: Res<Input<KeyCode>>, mut net: ResMut<NetView>, mut focus: ResMut<FaceFocus>) {
+    if keys.just_pressed(KeyCode::N) {
+        net.open = !net.open;
+        if net.open {
+            focus.face = Some(Direction3D::ZPosi);
+        }
+    }
+}
+
+/// Moves every face plane to where its face has got to in the opening, following its cube as it
+/// slides, and fades the bodies out while the net is open.
+pub fn unfold_faces(
+    time: Res<Clock>,
+    resource: Res<CubesResource>,
+    assets: Res<CubeAssets>,
+    mut net: ResMut<NetView>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    query_bodies: Query<&Transform, With<CubeHome>>,
+    mut query_faces: Query<
+        (&CubeFace, Option<&ReversedFace>, &Parent, &mut Transform),
+        Without<CubeHome>,
+    >,
+) {
+    let target = if net.open { 1.0 } else { 0.0 };
+    if net.progress != target {
+        let step = time.delta_seconds() / NetView::DURATION;
+        net.progress = if net.open {
+            (net.progress + step).min(1.0)
+        } else {
+            (net.progress - step).max(0.0)
+        };
+    } else if net.progress == 0.0 {
+        // Folded and still: the planes already sit where `put_cubes` put them.
+        return;
+    }
+    let progress = net.progress;
+
+    if let Some(material) = materials.get_mut(&assets.body_material) {
+        material.base_color.set_a(0.4 * (1.0 - progress));
+    }
+    if let Some(material) = materials.get_mut(&assets.obstacle_material) {
+        material.base_color.set_a(0.9 * (1.0 - progress));
+    }
+
+    for (face, reversed, parent, mut tr) in query_faces.iter_mut() {
+        if let Ok(body) = query_bodies.get(parent.0) {
+            let base = face_transform(&resource, face.0, reversed.is_some());
+            let world = unfold(&resource, face.0, progress) * *body * base;
+            *tr = Transform::from_matrix(body.compute_matrix().inverse() * world.compute_matrix());
+        }
+    }
+}
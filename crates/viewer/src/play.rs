@@ -0,0 +1,239 @@
+use crate::belts::{turn_at, BeltControl, Puzzle};
+use crate::components::PuzzleCamera;
+use crate::events::CubeSlid;
+use crate::net::{unfold, NetView};
+use crate::resources::{BoardResource, CubesResource};
+use alignment::board::{Board, Move};
+use alignment::model::*;
+use bevy::prelude::*;
+use std::cmp::Ordering;
+
+/// Whether the board shuffles itself, waits for the player or plays a recorded game back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PlayMode {
+    #[default]
+    Demo,
+    Manual,
+    Replay,
+}
+
+/// Switches between the shuffle demo and manual play with Space.
+///
+/// During a replay it hands the board over to the player where the replay has got to.
+pub fn toggle_mode(keys: Res<Input<KeyCode>>, mut mode: ResMut<PlayMode>) {
+    if keys.just_pressed(KeyCode::Space) {
+        *mode = match *mode {
+            PlayMode::Demo | PlayMode::Replay => PlayMode::Manual,
+            PlayMode::Manual => PlayMode::Demo,
+        };
+        info!("Play mode: {:?}", *mode);
+    }
+}
+
+/// Slides the clicked cube toward the hole when they share a straight surface line, shifting
+/// every cube between them along with it. On the belts the click turns the belt through the cube
+/// around the chosen axis instead, back against its order while Shift is held.
+///
+/// The left button also orbits the camera, so only a press released without dragging counts.
+#[allow(clippy::too_many_arguments)]
+pub fn click(
+    windows: Res<Windows>,
+    buttons: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    mode: Res<PlayMode>,
+    resource: Res<CubesResource>,
+    net: Res<NetView>,
+    control: Res<BeltControl>,
+    mut board: ResMut<BoardResource>,
+    mut pressed_at: Local<Option<Vec2>>,
+    query_camera: Query<(&Camera, &GlobalTransform), With<PuzzleCamera>>,
+    mut events: EventWriter<CubeSlid>,
+) {
+    const CLICK_SLOP: f32 = 4.0;
+
+    if *mode != PlayMode::Manual {
+        return;
+    }
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+    if buttons.just_pressed(MouseButton::Left) {
+        *pressed_at = window.cursor_position();
+    }
+    if !buttons.just_released(MouseButton::Left) {
+        return;
+    }
+    let released_at = window.cursor_position();
+    let clicked = match (pressed_at.take(), released_at) {
+        (Some(a), Some(b)) => a.distance(b) <= CLICK_SLOP,
+        _ => false,
+    };
+    if !clicked {
+        return;
+    }
+
+    let picked = query_camera
+        .iter()
+        .find_map(|(camera, gt)| cursor_ray(window, camera, gt))
+        .and_then(|(origin, dir)| pick_cube(&resource, &board, &net, origin, dir));
+    let pos = match picked {
+        Some(pos) => pos,
+        None => return,
+    };
+    let slid = match resource.puzzle {
+        Puzzle::Slide => match line_to_hole(&board.board, pos) {
+            Some(mv) => board.shift(mv),
+            None => {
+                info!("{:?} is not in line with the hole {:?}", pos, board.hole());
+                return;
+            }
+        },
+        Puzzle::Belts => {
+            let back = keys.pressed(KeyCode::LShift) || keys.pressed(KeyCode::RShift);
+            match turn_at(&board.board, pos, control.axis, back) {
+                Some(turn) => board.turn(turn),
+                None => {
+                    info!("No belt around {} turns {:?}", control.axis.name(), pos);
+                    return;
+                }
+            }
+        }
+    };
+    slid.into_iter().for_each(|slid| events.send(slid));
+}
+
+/// The move shifting the fewest cubes from `pos` into a hole, if any line leads there.
+pub fn line_to_hole(board: &Board, pos: Pos3D) -> Option<Move> {
+    board
+        .line_moves()
+        .into_iter()
+        .filter(|mv| mv.src == pos)
+        .min_by_key(|mv| (mv.cubes, mv.direction as u8))
+}
+
+/// World space origin and direction of the ray under the mouse cursor.
+pub fn cursor_ray(window: &Window, camera: &Camera, gt: &GlobalTransform) -> Option<(Vec3, Vec3)> {
+    let cursor = window.cursor_position()?;
+    let screen = Vec2::new(window.width(), window.height());
+    let ndc = (cursor / screen) * 2.0 - Vec2::ONE;
+    let ndc_to_world = gt.compute_matrix() * camera.projection_matrix.inverse();
+    // The perspective projection is reversed-z, so the near plane sits at depth 1.
+    let near = ndc_to_world.project_point3(ndc.extend(1.0));
+    let far = ndc_to_world.project_point3(ndc.extend(0.5));
+    Some((near, (far - near).normalize()))
+}
+
+/// The occupied cell whose cube the ray hits first.
+///
+/// While the net is open a cube is hit wherever one of its faces has been carried to.
+pub fn pick_cube(
+    resource: &CubesResource,
+    board: &BoardResource,
+    net: &NetView,
+    origin: Vec3,
+    dir: Vec3,
+) -> Option<Pos3D> {
+    let half = Vec3::splat(resource.cube_size / 2.0);
+    board
+        .board
+        .parts()
+        .keys()
+        .flat_map(|pos| {
+            let center = resource.calc_center(*pos);
+            pos.get_faces(resource.spaces)
+                .into_iter()
+                .map(move |d| (*pos, unfold(resource, d, net.progress).mul_vec3(center)))
+        })
+        .filter_map(|(pos, center)| {
+            ray_box(origin, dir, center - half, center + half).map(|t| (t, pos))
+        })
+        .min_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+        .map(|(_, pos)| pos)
+}
+
+fn ray_box(origin: Vec3, dir: Vec3, min: Vec3, max: Vec3) -> Option<f32> {
+    let inv = dir.recip();
+    let t1 = (min - origin) * inv;
+    let t2 = (max - origin) * inv;
+    let near = t1.min(t2).max_element();
+    let far = t1.max(t2).min_element();
+    (near <= far && far >= 0.0).then(|| near.max(0.0))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::animation::CubeAnimation;
+    use crate::harness::Harness;
+    use crate::hud::PlayStats;
+    use crate::save::GameLog;
+
+    #[test]
+    fn rays_hit_the_nearest_side() {
+        let (min, max) = (Vec3::splat(-1.0), Vec3::splat(1.0));
+        assert_eq!(
+            ray_box(Vec3::new(0.0, 0.0, 5.0), -Vec3::Z, min, max),
+            Some(4.0)
+        );
+        assert_eq!(ray_box(Vec3::new(3.0, 0.0, 5.0), -Vec3::Z, min, max), None);
+        assert_eq!(ray_box(Vec3::new(0.0, 0.0, 5.0), Vec3::Z, min, max), None);
+        assert_eq!(ray_box(Vec3::ZERO, Vec3::X, min, max), Some(0.0));
+    }
+
+    #[test]
+    fn clicks_shift_lines_toward_the_hole() {
+        let mut harness = Harness::manual();
+        harness.advance(0.1);
+        let board = harness.board().board.clone();
+        let hole = harness.board().hole().unwrap();
+        // Lines run round the box, so a cell may reach the hole both ways; a click takes the
+        // shorter.
+        let lines = board.line_moves();
+        let mv = *lines
+            .iter()
+            .filter(|mv| mv.cubes > 1)
+            .find(|mv| {
+                lines
+                    .iter()
+                    .all(|other| other.src != mv.src || other.cubes >= mv.cubes)
+            })
+            .unwrap();
+        assert_eq!(
+            line_to_hole(&board, mv.src).map(|mv| mv.cubes),
+            Some(mv.cubes)
+        );
+        let neighbour = board.moves()[0];
+        assert_eq!(line_to_hole(&board, neighbour.src), Some(neighbour));
+
+        assert!(harness.slide(mv));
+        harness.advance(0.1);
+        assert_eq!(harness.board().hole(), Some(mv.src));
+        assert!(harness.board().board.get(hole).is_some());
+        assert_eq!(harness.resource::<CubeAnimation>().pending(), 1);
+        assert_eq!(harness.resource::<PlayStats>().moves, 1);
+        let game = harness.resource::<GameLog>().game.clone().unwrap();
+        assert_eq!(
+            game.moves.iter().map(|timed| timed.mv).collect::<Vec<_>>(),
+            vec![mv]
+        );
+
+        harness.run_for(1.0, 0.25);
+        assert_eq!(harness.resource::<CubeAnimation>().pending(), 0);
+        harness.bodies().into_iter().for_each(|(_, pos, tr)| {
+            assert_eq!(
+                tr.translation,
+                harness.resource::<CubesResource>().calc_center(pos)
+            )
+        });
+    }
+
+    #[test]
+    fn space_switches_modes() {
+        let mut harness = Harness::manual();
+        harness.tap(KeyCode::Space);
+        assert_eq!(*harness.resource::<PlayMode>(), PlayMode::Demo);
+        harness.tap(KeyCode::Space);
+        assert_eq!(*harness.resource::<PlayMode>(), PlayMode::Manual);
+    }
+}
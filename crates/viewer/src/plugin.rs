@@ -0,0 +1,218 @@
+use crate::animation::{self, AnimationSettings, CubeAnimation};
+use crate::belts::{self, BeltControl};
+use crate::clock::{self, Clock};
+use crate::config::ViewerConfig;
+use crate::events::{CubeSlid, PuzzleSolved};
+use crate::faces::{self, FaceArt};
+use crate::heat::{self, HeatMap};
+use crate::hint::{self, Hint};
+use crate::hud::{self, PlayStats};
+use crate::move_cubes;
+use crate::net::{self, NetView};
+use crate::play::{self, PlayMode};
+use crate::put_cubes;
+use crate::replay::{self, Replay};
+use crate::resources::*;
+use crate::rotate::{self, FaceFocus};
+use crate::save::{self, GameLog};
+use crate::settings;
+use crate::ui::{self, UiFont};
+use crate::victory::{self, Victory};
+use bevy::core::CoreSystem;
+use bevy::prelude::*;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, SystemLabel)]
+pub enum ViewerSystem {
+    Tick,
+    Configure,
+    Build,
+    Shuffle,
+    Animate,
+    Track,
+    Record,
+    Focus,
+    Camera,
+    Hint,
+}
+
+/// The whole puzzle, for any Bevy app that already has the default plugins.
+///
+/// The host decides which of the camera, lights, overlays and input the puzzle owns. Whatever it
+/// keeps for itself it drives through `ViewerConfig`, `PlayMode` and the `CubeSlid` and
+/// `PuzzleSolved` events instead.
+#[derive(Debug, Clone)]
+pub struct SlidePuzzlePlugin {
+    pub config: ViewerConfig,
+    /// Read the keyboard, mouse and touch screen.
+    pub input: bool,
+    /// Spawn and steer an orbiting camera; without it, tag a camera with `PuzzleCamera` to pick
+    /// cubes through it.
+    pub camera: bool,
+    /// Spawn a UI camera with the HUD, the settings panel and the heat-map legend.
+    pub ui: bool,
+    pub lights: bool,
+}
+
+impl Default for SlidePuzzlePlugin {
+    fn default() -> Self {
+        SlidePuzzlePlugin {
+            config: ViewerConfig::default(),
+            input: true,
+            camera: true,
+            ui: true,
+            lights: true,
+        }
+    }
+}
+
+impl Plugin for SlidePuzzlePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.config.clone());
+        app.init_resource::<CubesResource>();
+        app.init_resource::<BoardResource>();
+        app.init_resource::<CubeAssets>();
+        app.init_resource::<AnimationSettings>();
+        app.init_resource::<CubeAnimation>();
+        app.init_resource::<FaceArt>();
+        app.init_resource::<PlayMode>();
+        app.init_resource::<PlayStats>();
+        app.init_resource::<FaceFocus>();
+        app.init_resource::<NetView>();
+        app.init_resource::<Hint>();
+        app.init_resource::<HeatMap>();
+        app.init_resource::<GameLog>();
+        app.init_resource::<Replay>();
+        app.init_resource::<Victory>();
+        app.init_resource::<BeltControl>();
+        app.init_resource::<Clock>();
+        app.add_event::<CubeSlid>();
+        app.add_event::<PuzzleSolved>();
+
+        app.add_system_to_stage(
+            CoreStage::First,
+            clock::tick
+                .label(ViewerSystem::Tick)
+                .after(CoreSystem::Time),
+        );
+        app.add_system(settings::apply.label(ViewerSystem::Configure));
+        app.add_system(
+            put_cubes::build
+                .label(ViewerSystem::Build)
+                .after(ViewerSystem::Configure),
+        );
+        app.add_system(
+            move_cubes::shuffle
+                .label(ViewerSystem::Shuffle)
+                .after(ViewerSystem::Build),
+        );
+        app.add_system(
+            belts::spin
+                .label(ViewerSystem::Shuffle)
+                .after(ViewerSystem::Build),
+        );
+        app.add_startup_system(replay::load);
+        app.add_system(
+            replay::play
+                .after(ViewerSystem::Build)
+                .before(ViewerSystem::Animate),
+        );
+        app.add_system(
+            animation::animate
+                .label(ViewerSystem::Animate)
+                .after(ViewerSystem::Shuffle),
+        );
+        app.add_system(
+            hud::track
+                .label(ViewerSystem::Track)
+                .after(ViewerSystem::Animate),
+        );
+        app.add_system(
+            save::record
+                .label(ViewerSystem::Record)
+                .after(ViewerSystem::Track),
+        );
+        app.add_system(save::autosave.after(ViewerSystem::Record));
+        app.add_system(victory::celebrate.after(ViewerSystem::Track));
+        app.add_system(faces::retexture.after(ViewerSystem::Build));
+        app.add_system(net::unfold_faces.after(ViewerSystem::Animate));
+        app.add_system(
+            hint::follow
+                .label(ViewerSystem::Hint)
+                .after(ViewerSystem::Animate),
+        );
+        app.add_system(hint::show.after(ViewerSystem::Hint));
+        app.add_system(hint::pulse);
+        app.add_system(heat::tint.after(ViewerSystem::Animate));
+
+        if self.lights {
+            app.add_startup_system(lights);
+        }
+        if self.camera {
+            app.add_startup_system(rotate::setup);
+            app.add_system(rotate::frame.after(ViewerSystem::Configure));
+            app.add_system(
+                rotate::follow_hole
+                    .label(ViewerSystem::Focus)
+                    .after(ViewerSystem::Animate),
+            );
+            app.add_system(
+                rotate::focus_face
+                    .after(ViewerSystem::Focus)
+                    .after(ViewerSystem::Camera),
+            );
+        }
+        if self.ui {
+            app.init_resource::<UiFont>();
+            app.add_startup_system(ui::setup);
+            app.add_startup_system(settings::setup);
+            app.add_startup_system(hud::setup);
+            app.add_startup_system(heat::setup);
+            app.add_startup_system(save::setup);
+            app.add_startup_system(victory::setup);
+            app.add_system(settings::press);
+            app.add_system(settings::refresh);
+            app.add_system(hud::show.after(ViewerSystem::Track));
+            app.add_system(save::prompt);
+            app.add_system(victory::show);
+        }
+        if self.input {
+            if self.camera {
+                app.add_system(rotate::action.label(ViewerSystem::Camera));
+            }
+            if self.ui {
+                app.add_system(settings::toggle);
+            }
+            app.add_system(rotate::snap_keys.label(ViewerSystem::Focus));
+            app.add_system(net::toggle.label(ViewerSystem::Focus));
+            app.add_system(faces::cycle_theme.before(ViewerSystem::Configure));
+            app.add_system(animation::cycle_easing);
+            app.add_system(belts::cycle_axis);
+            app.add_system(play::toggle_mode.before(ViewerSystem::Shuffle));
+            app.add_system(save::resume.before(ViewerSystem::Configure));
+            app.add_system(replay::control.before(ViewerSystem::Configure));
+            app.add_system(
+                play::click
+                    .after(ViewerSystem::Build)
+                    .before(ViewerSystem::Animate),
+            );
+            app.add_system(
+                hint::request
+                    .label(ViewerSystem::Hint)
+                    .after(ViewerSystem::Animate),
+            );
+            app.add_system(heat::toggle);
+        }
+    }
+}
+
+fn lights(mut commands: Commands) {
+    // light
+    commands.spawn_bundle(PointLightBundle {
+        transform: Transform::from_translation(Vec3::new(4.0, 8.0, 4.0)),
+        ..Default::default()
+    });
+    commands.spawn_bundle(PointLightBundle {
+        transform: Transform::from_translation(Vec3::new(-10.0, -20.0, -10.0)),
+        ..Default::default()
+    });
+}
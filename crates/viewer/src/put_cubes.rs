@@ -1,196 +1,343 @@
+use crate::animation::CubeAnimation;
 use crate::components::*;
-use crate::resources::CubesResource;
+use crate::faces::{face_atlas, FaceArt};
+use crate::resources::{BoardResource, CubeAssets, CubesResource};
 use alignment::model::*;
 use bevy::prelude::*;
-use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
-use shuffle::rand_hole;
+use bevy::render::mesh::VertexAttributeValues;
+use std::collections::HashMap;
+use textures::pattern::FaceSpec;
 
-pub fn setup(
-    resource: ResMut<CubesResource>,
+/// Face planes stand a little proud of the body so they never fight it for depth.
+const FACE_SCALE: f32 = 0.96;
+
+/// Spawns the cubes, replacing any previous board whenever `CubesResource` changes.
+///
+/// Every body shares one mesh and material, and every face plane samples a tile of a single atlas
+/// through one shared material. The plane meshes carrying the tile UVs are made once per tile and
+/// kept until the box or the cube size changes; both sides of a face plane share one, the inner
+/// side mirrored through its transform.
+#[allow(clippy::too_many_arguments)]
+pub fn build(
+    resource: Res<CubesResource>,
+    art: Res<FaceArt>,
+    mut board: ResMut<BoardResource>,
+    mut assets: ResMut<CubeAssets>,
+    mut animation: ResMut<CubeAnimation>,
     mut commands: Commands,
     mut images: ResMut<Assets<Image>>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
 ) {
-    let hole = rand_hole(resource.spaces);
-    let cubes = generate_surfaces(resource.spaces)
-        .into_iter()
-        .filter(|home| *home != hole);
+    if !resource.is_changed() {
+        return;
+    }
+
+    let board = &mut *board;
+    board
+        .entities
+        .values()
+        .chain(board.walls.iter())
+        .for_each(|entity| {
+            commands.entity(*entity).despawn_recursive();
+        });
+    board.reset(resource.spaces, resource.goal, resource.puzzle);
+    animation.clear();
 
     let body_size = resource.cube_size * 0.95;
-    let face_size = resource.cube_size * 0.96;
-    let face_half = face_size / 2.0;
+    let face_size = resource.cube_size * FACE_SCALE;
 
-    commands.spawn().insert(CubeHole(hole));
+    // Every cell of the surface gets its tiles, whether or not a cube starts there, so the tiles
+    // stay put from one board to the next.
+    let spaces = resource.spaces;
+    let planes: Vec<FaceSpec> = generate_surfaces(spaces)
+        .into_iter()
+        .flat_map(|home| {
+            home.get_faces(spaces)
+                .into_iter()
+                .map(move |direction| FaceSpec::new(spaces, home, direction, false))
+        })
+        .collect();
+
+    let mut atlas = face_atlas(planes.len());
+    if assets.planes != planes || assets.plane_size != face_size {
+        assets.plane_meshes = (0..planes.len())
+            .map(|i| meshes.add(plane_mesh(face_size, atlas.uv_rect(i as u32))))
+            .collect();
+        assets.planes = planes;
+        assets.plane_size = face_size;
+    }
+    let tiles: HashMap<FaceSpec, usize> = assets
+        .planes
+        .iter()
+        .enumerate()
+        .map(|(i, spec)| (*spec, i))
+        .collect();
+    art.paint(&images, &mut atlas, &assets.planes);
+    let atlas_image = art.atlas_image(&images, atlas);
+    let atlas_image = images.add(atlas_image);
 
-    cubes.into_iter().for_each(|home| {
-        let center = resource.calc_center(home);
+    let mut body_material: StandardMaterial = Color::rgba(1.0, 1.0, 1.0, 0.4).into();
+    body_material.alpha_mode = AlphaMode::Blend;
+    assets.body_mesh = meshes.add(Mesh::from(shape::Cube { size: body_size }));
+    assets.body_material = materials.add(body_material);
+    let mut obstacle_material: StandardMaterial = Color::rgba(0.15, 0.15, 0.18, 0.9).into();
+    obstacle_material.alpha_mode = AlphaMode::Blend;
+    assets.obstacle_material = materials.add(obstacle_material);
+    assets.face_material = materials.add(atlas_image.into());
 
-        let mut mate: StandardMaterial = Color::rgba(1.0, 1.0, 1.0, 0.4).into();
-        mate.alpha_mode = AlphaMode::Blend;
-        commands
-            .spawn()
+    board.board.parts().iter().for_each(|(pos, cube)| {
+        let home = cube.home();
+        let center = resource.calc_center(*pos);
+
+        let pinned = board.board.pins().contains(pos);
+        let material = if pinned {
+            &assets.obstacle_material
+        } else {
+            &assets.body_material
+        };
+        let mut body = commands.spawn_bundle(PbrBundle {
+            mesh: assets.body_mesh.clone(),
+            material: material.clone(),
+            transform: Transform::from_translation(center),
+            ..Default::default()
+        });
+        if pinned {
+            body.insert(Pinned);
+        }
+        let entity = body
             .insert(CubeHome(home))
-            .insert(CubePos(home))
-            .insert_bundle(PbrBundle {
-                mesh: meshes.add(Mesh::from(shape::Cube { size: body_size })),
-                material: materials.add(mate),
-                transform: Transform::from_translation(center),
-                ..Default::default()
-            });
-
-        home.get_faces(resource.spaces)
-            .into_iter()
-            .for_each(|direction| {
-                [true, false].into_iter().for_each(|reversed| {
-                    let mut tr = mk_transform(direction, reversed);
-                    tr.translation *= face_half;
-                    tr.translation += center;
-
-                    let image = draw_image(100, resource.spaces, home, direction, reversed);
-
-                    commands
-                        .spawn()
-                        .insert(CubeHome(home))
-                        .insert(CubePos(home))
-                        .insert(CubeFace(direction))
-                        .insert_bundle(PbrBundle {
-                            mesh: meshes.add(shape::Plane { size: face_size }.into()),
-                            material: materials.add(images.add(image).into()),
-                            transform: tr,
-                            ..Default::default()
+            .with_children(|parent| {
+                home.get_faces(resource.spaces)
+                    .into_iter()
+                    .for_each(|direction| {
+                        let tile = tiles[&FaceSpec::new(spaces, home, direction, false)];
+                        [true, false].into_iter().for_each(|reversed| {
+                            let tr = face_transform(&resource, direction, reversed);
+                            let mut face = parent.spawn_bundle(PbrBundle {
+                                mesh: assets.plane_meshes[tile].clone(),
+                                material: assets.face_material.clone(),
+                                transform: tr,
+                                ..Default::default()
+                            });
+                            face.insert(CubeFace(direction));
+                            if reversed {
+                                face.insert(ReversedFace);
+                            }
                         });
-                });
-            });
+                    });
+            })
+            .id();
+        board.entities.insert(home, entity);
     });
+
+    board.walls = board
+        .board
+        .walls()
+        .iter()
+        .map(|pos| {
+            commands
+                .spawn_bundle(PbrBundle {
+                    mesh: assets.body_mesh.clone(),
+                    material: assets.obstacle_material.clone(),
+                    transform: Transform::from_translation(resource.calc_center(*pos)),
+                    ..Default::default()
+                })
+                .insert(Wall(*pos))
+                .id()
+        })
+        .collect();
+}
+
+/// Where a face plane sits on its cube body while the box is folded.
+///
+/// The inner side is the outer one mirrored through the plane, which turns it to face into the
+/// body and shows the tile as seen through it.
+pub fn face_transform(
+    resource: &CubesResource,
+    direction: Direction3D,
+    reversed: bool,
+) -> Transform {
+    let mut tr = mk_transform(direction);
+    tr.translation *= resource.cube_size * FACE_SCALE / 2.0;
+    if reversed {
+        tr.scale = Vec3::new(1.0, -1.0, 1.0);
+    }
+    tr
 }
 
-fn mk_transform(direction: Direction3D, reversed: bool) -> Transform {
-    let rev: f32 = if reversed { 180.0 } else { 0.0 };
+/// A plane whose texture coordinates span only `(u0, v0, u1, v1)` of the atlas.
+fn plane_mesh(size: f32, (u0, v0, u1, v1): (f32, f32, f32, f32)) -> Mesh {
+    let mut mesh = Mesh::from(shape::Plane { size });
+    if let Some(VertexAttributeValues::Float32x2(uvs)) = mesh.attribute_mut(Mesh::ATTRIBUTE_UV_0) {
+        uvs.iter_mut().for_each(|[u, v]| {
+            *u = u0 + *u * (u1 - u0);
+            *v = v0 + *v * (v1 - v0);
+        });
+    }
+    mesh
+}
+
+fn mk_transform(direction: Direction3D) -> Transform {
     match direction {
         Direction3D::XNega => Transform {
             translation: Vec3::new(-1.0, 0.0, 0.0),
-            rotation: Quat::from_rotation_z((rev + 90.0).to_radians()),
+            rotation: Quat::from_rotation_z(90.0_f32.to_radians()),
             ..Default::default()
         },
         Direction3D::XPosi => Transform {
             translation: Vec3::new(1.0, 0.0, 0.0),
-            rotation: Quat::from_rotation_z((rev - 90.0).to_radians()),
+            rotation: Quat::from_rotation_z((-90.0_f32).to_radians()),
             ..Default::default()
         },
         Direction3D::YNega => Transform {
             translation: Vec3::new(0.0, -1.0, 0.0),
-            rotation: Quat::from_rotation_z((rev + 180.0).to_radians()),
+            rotation: Quat::from_rotation_z(180.0_f32.to_radians()),
             ..Default::default()
         },
         Direction3D::YPosi => Transform {
             translation: Vec3::new(0.0, 1.0, 0.0),
-            rotation: Quat::from_rotation_z(rev.to_radians()),
+            rotation: Quat::from_rotation_z(0.0),
             ..Default::default()
         },
         Direction3D::ZNega => Transform {
             translation: Vec3::new(0.0, 0.0, -1.0),
-            rotation: Quat::from_rotation_x((rev - 90.0).to_radians()),
+            rotation: Quat::from_rotation_x((-90.0_f32).to_radians()),
             ..Default::default()
         },
         Direction3D::ZPosi => Transform {
             translation: Vec3::new(0.0, 0.0, 1.0),
-            rotation: Quat::from_rotation_x((rev + 90.0).to_radians()),
+            rotation: Quat::from_rotation_x(90.0_f32.to_radians()),
             ..Default::default()
         },
     }
 }
 
-fn draw_image(
-    image_size: u8,
-    spaces: Size3D,
-    pos: Pos3D,
-    direction: Direction3D,
-    reversed: bool,
-) -> Image {
-    let image_width = image_size as f32;
-    let image_height = image_size as f32;
-
-    let (width, height) = match direction {
-        Direction3D::XNega => (spaces.z(), spaces.y()),
-        Direction3D::XPosi => (spaces.z(), spaces.y()),
-        Direction3D::YNega => (spaces.z(), spaces.x()),
-        Direction3D::YPosi => (spaces.z(), spaces.x()),
-        Direction3D::ZNega => (spaces.y(), spaces.x()),
-        Direction3D::ZPosi => (spaces.y(), spaces.x()),
-    };
-
-    let (pos_x, pos_y) = match direction {
-        Direction3D::XNega => (pos.z(), pos.y()),
-        Direction3D::XPosi => (pos.z(), pos.y()),
-        Direction3D::YNega => (pos.z(), pos.x()),
-        Direction3D::YPosi => (pos.z(), pos.x()),
-        Direction3D::ZNega => (pos.y(), pos.x()),
-        Direction3D::ZPosi => (pos.y(), pos.x()),
-    };
-
-    let (sig_x, sig_y) = match direction {
-        Direction3D::XNega => (false, !reversed),
-        Direction3D::XPosi => (false, reversed),
-        Direction3D::YNega => (false, reversed),
-        Direction3D::YPosi => (false, !reversed),
-        Direction3D::ZNega => (reversed, true),
-        Direction3D::ZPosi => (!reversed, true),
-    };
-
-    let base_color = match direction {
-        Direction3D::XNega => Color::RED,
-        Direction3D::XPosi => Color::CYAN,
-        Direction3D::YNega => Color::GREEN,
-        Direction3D::YPosi => Color::PURPLE,
-        Direction3D::ZNega => Color::BLUE,
-        Direction3D::ZPosi => Color::YELLOW,
-    };
-
-    let mut image_data = Vec::with_capacity((image_width * image_height) as usize * 4);
-    let mut put_color = |c: Color| {
-        [c.r(), c.g(), c.b(), c.a()].into_iter().for_each(|v| {
-            image_data.push((v * 255.0) as u8);
-        });
-    };
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::ViewerConfig;
+    use crate::harness::Harness;
+    use std::collections::HashSet;
 
-    let r_units = image_width.max(image_height) / 2.0;
-    let center_x = image_width * width as f32 / 2.0;
-    let center_y = image_height * height as f32 / 2.0;
+    fn planes(harness: &Harness, spaces: Size3D) -> usize {
+        harness
+            .board()
+            .board
+            .parts()
+            .values()
+            .map(|cube| cube.home().get_faces(spaces).len())
+            .sum()
+    }
 
-    let normalize = |size: f32, pos: u8, p: u8, sig: bool| -> f32 {
-        let base = size * pos as f32;
-        if sig {
-            base + p as f32
-        } else {
-            base + (size - p as f32 - 1.0)
-        }
-    };
-
-    for x in 0..(image_width as u8) {
-        for y in 0..(image_height as u8) {
-            let pixel_x = normalize(image_width, pos_x, x, sig_x);
-            let pixel_y = normalize(image_height, pos_y, y, sig_y);
-            let d_x = pixel_x - center_x;
-            let d_y = pixel_y - center_y;
-            let d = (d_x.powf(2.0) + d_y.powf(2.0)).sqrt();
-            let u = (d / r_units) as u8;
-            if u % 2 == 1 {
-                put_color(base_color);
-            } else {
-                put_color(Color::WHITE);
-            }
-        }
+    #[test]
+    fn build_spawns_every_cube() {
+        let mut harness = Harness::manual();
+        let spaces = harness.resource::<CubesResource>().spaces;
+        let cubes = harness.board().board.parts().len();
+        assert_eq!(harness.bodies().len(), cubes);
+        assert_eq!(harness.board().entities.len(), cubes);
+        let count_faces = |harness: &mut Harness| {
+            harness
+                .app
+                .world
+                .query::<&CubeFace>()
+                .iter(&harness.app.world)
+                .count()
+        };
+        // Both sides of a plane are drawn from one tile and one mesh, and every cell of the
+        // surface has its tiles.
+        assert_eq!(count_faces(&mut harness), planes(&harness, spaces) * 2);
+        let tiles: usize = generate_surfaces(spaces)
+            .into_iter()
+            .map(|home| home.get_faces(spaces).len())
+            .sum();
+        let face_meshes = |harness: &mut Harness| {
+            harness
+                .app
+                .world
+                .query_filtered::<&Handle<Mesh>, With<CubeFace>>()
+                .iter(&harness.app.world)
+                .map(|mesh| mesh.id)
+                .collect::<HashSet<_>>()
+        };
+        let meshes = face_meshes(&mut harness);
+        assert_eq!(harness.resource::<CubeAssets>().planes.len(), tiles);
+        assert_eq!(harness.resource::<CubeAssets>().plane_meshes.len(), tiles);
+        assert_eq!(meshes.len(), planes(&harness, spaces));
+
+        // Another board of the same size keeps the meshes.
+        let kept = harness.resource::<CubeAssets>().plane_meshes.clone();
+        harness.resource_mut::<CubesResource>().set_changed();
+        harness.advance(0.1);
+        assert_eq!(harness.resource::<CubeAssets>().plane_meshes, kept);
+        let kept: HashSet<_> = kept.iter().map(|mesh| mesh.id).collect();
+        assert!(face_meshes(&mut harness).is_subset(&kept));
+        harness.bodies().into_iter().for_each(|(home, pos, tr)| {
+            assert_eq!(home.0, pos);
+            assert_eq!(
+                tr.translation,
+                harness.resource::<CubesResource>().calc_center(pos)
+            );
+        });
+
+        // A new size replaces every entity of the old board.
+        let spaces = Size3D::new(2, 3, 4);
+        harness.resource_mut::<ViewerConfig>().spaces = spaces;
+        harness.advance(0.1);
+        let cubes = harness.board().board.parts().len();
+        assert_eq!(cubes, generate_surfaces(spaces).len() - 1);
+        assert_eq!(harness.bodies().len(), cubes);
+        assert_eq!(count_faces(&mut harness), planes(&harness, spaces) * 2);
     }
 
-    Image::new(
-        Extent3d {
-            width: image_width as u32,
-            height: image_height as u32,
-            ..Default::default()
-        },
-        TextureDimension::D2,
-        image_data,
-        TextureFormat::Rgba8Unorm,
-    )
+    #[test]
+    fn obstacles_stand_apart() {
+        let mut harness = Harness::manual();
+        let spaces = harness.resource::<CubesResource>().spaces;
+        let hole = harness.board().hole().unwrap();
+        let cells: Vec<Pos3D> = harness.board().board.parts().keys().copied().collect();
+        let (wall, pin) = (cells[0], cells[1]);
+        let level = harness
+            .board()
+            .board
+            .clone()
+            .with_obstacles([wall].into_iter().collect(), [pin].into_iter().collect())
+            .unwrap();
+        harness.resource_mut::<BoardResource>().queued = Some((level, 3));
+        harness.resource_mut::<CubesResource>().spaces = spaces;
+        harness.advance(0.1);
+
+        assert_eq!(harness.board().hole(), Some(hole));
+        assert_eq!(harness.board().walls.len(), 1);
+        assert_eq!(harness.bodies().len(), generate_surfaces(spaces).len() - 2);
+        let walls: Vec<Wall> = harness
+            .app
+            .world
+            .query::<&Wall>()
+            .iter(&harness.app.world)
+            .copied()
+            .collect();
+        assert_eq!(walls, vec![Wall(wall)]);
+        let pinned: Vec<Entity> = harness
+            .app
+            .world
+            .query_filtered::<Entity, With<Pinned>>()
+            .iter(&harness.app.world)
+            .collect();
+        assert_eq!(pinned, vec![harness.board().entities[&pin]]);
+        assert!(harness.board().distances.blocked().contains(&wall));
+
+        // A rebuild clears the walls away.
+        harness.resource_mut::<ViewerConfig>().spaces = Size3D::new(2, 3, 4);
+        harness.advance(0.1);
+        assert!(harness.board().walls.is_empty());
+        let count = harness
+            .app
+            .world
+            .query::<&Wall>()
+            .iter(&harness.app.world)
+            .count();
+        assert_eq!(count, 0);
+    }
 }
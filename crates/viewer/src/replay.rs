@@ -0,0 +1,241 @@
+use crate::clock::Clock;
+use crate::config::ViewerConfig;
+use crate::events::CubeSlid;
+use crate::hud::PlayStats;
+use crate::play::PlayMode;
+use crate::resources::{BoardResource, CubesResource};
+use crate::save::{self, GameLog};
+use alignment::format::Game;
+use bevy::prelude::*;
+
+/// Where X exports the game: a file on native, a `localStorage` key on wasm.
+const REPLAY_SLOT: &str = "slide-puzzle.replay";
+
+/// A recorded game played back at the pace it was played, or faster.
+pub struct Replay {
+    pub game: Option<Game>,
+    /// Index of the next move to play.
+    pub next: usize,
+    /// Seconds into the recorded game the playback has reached.
+    pub clock: f64,
+    /// How many times faster than it was played.
+    pub speed: f64,
+}
+
+impl FromWorld for Replay {
+    fn from_world(world: &mut World) -> Self {
+        let config = world.get_resource_or_insert_with(ViewerConfig::default);
+        Replay {
+            game: None,
+            next: 0,
+            clock: 0.0,
+            speed: config.replay_speed,
+        }
+    }
+}
+
+impl Replay {
+    /// Queues the board `game` started from and switches to playing it back.
+    pub fn start(
+        &mut self,
+        game: Game,
+        config: &mut ViewerConfig,
+        resource: &mut CubesResource,
+        board: &mut BoardResource,
+        mode: &mut PlayMode,
+    ) {
+        info!("Replaying a game of {} moves", game.moves.len());
+        save::queue(&game, game.start.clone(), config, resource, board);
+        *mode = PlayMode::Replay;
+        self.game = Some(game);
+        self.next = 0;
+        self.clock = 0.0;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.game
+            .as_ref()
+            .is_none_or(|game| game.moves.len() <= self.next)
+    }
+}
+
+/// Reads a game given as a share code, or failing that from where it was exported to, if the
+/// viewer can build its box.
+pub fn read(value: &str) -> Option<Game> {
+    Game::from_code(value)
+        .or_else(|| save::load(value).and_then(|text| Game::parse(&text)))
+        .and_then(save::playable)
+}
+
+/// Sets up the level or plays back the game the config names, if any.
+pub fn load(
+    mut config: ResMut<ViewerConfig>,
+    mut resource: ResMut<CubesResource>,
+    mut board: ResMut<BoardResource>,
+    mut mode: ResMut<PlayMode>,
+    mut replay: ResMut<Replay>,
+) {
+    if let Some(value) = config.level.clone() {
+        match read(&value) {
+            Some(game) => {
+                info!("Playing the level {}", value);
+                *mode = PlayMode::Manual;
+                save::queue(
+                    &game,
+                    game.start.clone(),
+                    &mut config,
+                    &mut resource,
+                    &mut board,
+                );
+            }
+            None => warn!("Could not read a level from {}", value),
+        }
+    }
+    let value = match config.replay.clone() {
+        Some(value) => value,
+        None => return,
+    };
+    match read(&value) {
+        Some(game) => replay.start(game, &mut config, &mut resource, &mut board, &mut mode),
+        None => warn!("Could not read a replay from {}", value),
+    }
+}
+
+/// P replays the game just played, X exports it, and the brackets change the playback speed.
+#[allow(clippy::too_many_arguments)]
+pub fn control(
+    keys: Res<Input<KeyCode>>,
+    time: Res<Clock>,
+    log: Res<GameLog>,
+    stats: Res<PlayStats>,
+    mut config: ResMut<ViewerConfig>,
+    mut resource: ResMut<CubesResource>,
+    mut board: ResMut<BoardResource>,
+    mut mode: ResMut<PlayMode>,
+    mut replay: ResMut<Replay>,
+) {
+    if keys.just_pressed(KeyCode::LBracket) || keys.just_pressed(KeyCode::RBracket) {
+        let factor = if keys.just_pressed(KeyCode::LBracket) {
+            0.5
+        } else {
+            2.0
+        };
+        replay.speed = (replay.speed * factor).clamp(
+            ViewerConfig::MIN_REPLAY_SPEED,
+            ViewerConfig::MAX_REPLAY_SPEED,
+        );
+        info!("Replay speed: {}x", replay.speed);
+    }
+
+    // The game in progress, otherwise the one last played back.
+    let game = match &log.game {
+        Some(game) => {
+            let mut game = game.clone();
+            game.seconds = stats.elapsed(time.seconds_since_startup());
+            Some(game)
+        }
+        None => replay.game.clone(),
+    };
+    let game = match game.filter(|game| !game.moves.is_empty()) {
+        Some(game) => game,
+        None => return,
+    };
+    if keys.just_pressed(KeyCode::X) {
+        save::store(REPLAY_SLOT, &game.to_text());
+        info!(
+            "Replay saved to {}; share code: {}",
+            REPLAY_SLOT,
+            game.to_code()
+        );
+    }
+    if keys.just_pressed(KeyCode::P) {
+        replay.start(game, &mut config, &mut resource, &mut board, &mut mode);
+    }
+}
+
+/// Makes every recorded move once the playback clock reaches the time it was made at.
+pub fn play(
+    mode: Res<PlayMode>,
+    time: Res<Clock>,
+    mut replay: ResMut<Replay>,
+    mut board: ResMut<BoardResource>,
+    mut stats: ResMut<PlayStats>,
+    mut events: EventWriter<CubeSlid>,
+) {
+    if *mode != PlayMode::Replay || replay.is_finished() {
+        return;
+    }
+
+    let replay = &mut *replay;
+    replay.clock += time.delta_seconds_f64() * replay.speed;
+    let moves = match &replay.game {
+        Some(game) => &game.moves,
+        None => return,
+    };
+    while let Some(timed) = moves
+        .get(replay.next)
+        .filter(|timed| timed.at <= replay.clock)
+    {
+        let slid = board.shift(timed.mv);
+        if slid.is_empty() {
+            warn!("Replay cannot make {}", timed.mv.notation());
+        }
+        slid.into_iter().for_each(|slid| events.send(slid));
+        replay.next += 1;
+    }
+    // The HUD shows the time of the recorded game rather than of the playback.
+    stats.started = Some(time.seconds_since_startup() - replay.clock);
+    if replay.next == moves.len() {
+        info!("Replay finished");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::harness::Harness;
+
+    #[test]
+    fn games_play_back_at_their_pace() {
+        let mut harness = Harness::manual();
+        harness.advance(0.1);
+        let start = harness.board().board.clone();
+        let first = start.moves()[0];
+        assert!(harness.slide(first));
+        harness.run_for(2.0, 0.5);
+        let second = harness.board().board.moves()[0];
+        assert!(harness.slide(second));
+        harness.advance(0.5);
+        let end = harness.board().board.clone();
+        let code = harness
+            .resource::<GameLog>()
+            .game
+            .as_ref()
+            .unwrap()
+            .to_code();
+
+        let game = read(&code).unwrap();
+        assert_eq!(game.moves.len(), 2);
+        assert!((game.moves[1].at - 2.0).abs() < 1e-6);
+
+        harness.resource_mut::<Replay>().speed = 2.0;
+        harness.tap(KeyCode::P);
+        assert_eq!(*harness.resource::<PlayMode>(), PlayMode::Replay);
+        assert_eq!(harness.resource::<Replay>().next, 1);
+        assert_ne!(harness.board().board, end);
+
+        // The second move was made two seconds in, which takes one second at double speed.
+        harness.run_for(0.9, 0.1);
+        assert_eq!(harness.resource::<Replay>().next, 1);
+        harness.run_for(0.2, 0.1);
+        assert!(harness.resource::<Replay>().is_finished());
+        assert_eq!(harness.board().board, end);
+    }
+
+    #[test]
+    fn codes_of_oversized_boxes_are_not_read() {
+        let code = "slide_1/size_200x200x200/hole_0,0,0";
+        assert!(Game::from_code(code).is_some());
+        assert!(read(code).is_none());
+    }
+}
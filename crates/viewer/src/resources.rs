@@ -1,16 +1,32 @@
-use alignment::model::{Pos3D, Size3D};
+use crate::belts::Puzzle;
+use crate::config::ViewerConfig;
+use crate::events::{CubeSlid, Step};
+use alignment::belt::Turn;
+use alignment::board::{Board, Move};
+use alignment::distance::DistanceTable;
+use alignment::goal::{Goal, GoalShape};
+use alignment::model::{Direction3D, Pos3D, Size3D};
 use bevy::prelude::*;
+use rand::prelude::*;
+use shuffle::rand_hole_with;
+use std::collections::HashMap;
+use textures::pattern::FaceSpec;
 
 pub struct CubesResource {
     pub spaces: Size3D,
     pub cube_size: f32,
+    pub goal: GoalShape,
+    pub puzzle: Puzzle,
 }
 
 impl FromWorld for CubesResource {
-    fn from_world(_: &mut World) -> Self {
+    fn from_world(world: &mut World) -> Self {
+        let config = world.get_resource_or_insert_with(ViewerConfig::default);
         CubesResource {
-            spaces: Size3D::new(3, 4, 5),
-            cube_size: 1.0,
+            spaces: config.spaces,
+            cube_size: config.cube_size,
+            goal: config.goal,
+            puzzle: config.puzzle,
         }
     }
 }
@@ -28,3 +44,169 @@ impl CubesResource {
         )
     }
 }
+
+/// Handles shared by every cube, and the outer side of every face plane in the order of their
+/// tiles in the atlas.
+#[derive(Default)]
+pub struct CubeAssets {
+    pub body_mesh: Handle<Mesh>,
+    pub body_material: Handle<StandardMaterial>,
+    /// Walls and the bodies of pinned cubes.
+    pub obstacle_material: Handle<StandardMaterial>,
+    pub face_material: Handle<StandardMaterial>,
+    pub planes: Vec<FaceSpec>,
+    /// The plane mesh sampling each tile, drawn on both sides of its face plane.
+    pub plane_meshes: Vec<Handle<Mesh>>,
+    /// The side of the plane meshes.
+    pub plane_size: f32,
+}
+
+/// The board the viewer is showing, and the body entity of every cube keyed by its home.
+pub struct BoardResource {
+    pub board: Board,
+    /// The shape of the goal in play, which is the identity when the chosen one does not fit.
+    pub shape: GoalShape,
+    pub goal: Goal,
+    pub distances: DistanceTable,
+    pub entities: HashMap<Pos3D, Entity>,
+    /// The wall entities, which belong to no cube.
+    pub walls: Vec<Entity>,
+    /// Seed of `rng`, kept so a saved game can tell how its board came about.
+    pub seed: u64,
+    /// Picks the first hole and every slide of the demo.
+    pub rng: StdRng,
+    /// A board and its seed to set up at the next reset instead of a fresh one.
+    pub queued: Option<(Board, u64)>,
+}
+
+impl FromWorld for BoardResource {
+    fn from_world(world: &mut World) -> Self {
+        let resource = world
+            .get_resource::<CubesResource>()
+            .expect("CubesResource must be initialized first");
+        let spaces = resource.spaces;
+        let (shape, goal) = fit_goal(resource.goal, spaces);
+        let seed = thread_rng().gen();
+        let mut rng = StdRng::seed_from_u64(seed);
+        let hole = rand_hole_with(&mut rng, spaces);
+        BoardResource {
+            board: fresh(&goal, hole, resource.puzzle),
+            shape,
+            goal,
+            distances: DistanceTable::new(spaces),
+            entities: HashMap::new(),
+            walls: vec![],
+            seed,
+            rng,
+            queued: None,
+        }
+    }
+}
+
+impl BoardResource {
+    /// Starts over toward the goal of `shape` with the queued board if it has `spaces`, otherwise
+    /// with a fresh board of `puzzle` and a new seed, forgetting every cube and wall entity.
+    pub fn reset(&mut self, spaces: Size3D, shape: GoalShape, puzzle: Puzzle) {
+        let (shape, goal) = fit_goal(shape, spaces);
+        self.shape = shape;
+        self.goal = goal;
+        match self
+            .queued
+            .take()
+            .filter(|(board, _)| board.size() == spaces)
+        {
+            Some((board, seed)) => {
+                self.seed = seed;
+                self.rng = StdRng::seed_from_u64(seed);
+                self.board = board;
+            }
+            None => {
+                self.seed = thread_rng().gen();
+                self.rng = StdRng::seed_from_u64(self.seed);
+                let hole = rand_hole_with(&mut self.rng, spaces);
+                self.board = fresh(&self.goal, hole, puzzle);
+            }
+        }
+        let blocked = self.board.blocked();
+        if self.distances.size() != spaces || *self.distances.blocked() != blocked {
+            self.distances = DistanceTable::with_blocked(spaces, blocked);
+        }
+        self.entities.clear();
+        self.walls.clear();
+    }
+
+    /// The first hole of the board; none while the belts are played.
+    pub fn hole(&self) -> Option<Pos3D> {
+        self.board.holes().first().copied()
+    }
+
+    pub fn is_solved(&self) -> bool {
+        self.goal.is_met(&self.board)
+    }
+
+    /// Slides the cube at `src` toward `d`, returning the event to announce it by.
+    pub fn slide(&mut self, src: Pos3D, d: Direction3D) -> Option<CubeSlid> {
+        self.shift(Move::new(src, d)).pop()
+    }
+
+    /// Makes `mv`, returning the events to announce every shifted cube by; none if it cannot be
+    /// made.
+    pub fn shift(&mut self, mv: Move) -> Vec<CubeSlid> {
+        let shifts = match self.board.shifts(mv) {
+            Some(shifts) => shifts,
+            None => return vec![],
+        };
+        let events = self.announce(shifts, Step::Move(mv));
+        if !self.board.apply(mv) {
+            return vec![];
+        }
+        events
+    }
+
+    /// Turns a belt, returning the events to announce every cube on it by; none if it cannot turn.
+    pub fn turn(&mut self, turn: Turn) -> Vec<CubeSlid> {
+        let events = self.announce(turn.steps(self.board.size()), Step::Turn(turn));
+        if !self.board.turn(turn) {
+            return vec![];
+        }
+        events
+    }
+
+    /// An event for every cube about to step, the first of them the lead.
+    fn announce(&self, steps: Vec<(Pos3D, Pos3D, Direction3D)>, step: Step) -> Vec<CubeSlid> {
+        steps
+            .into_iter()
+            .filter_map(|(from, to, direction)| {
+                self.board.get(from).map(|cube| (cube, from, to, direction))
+            })
+            .enumerate()
+            .map(|(i, (cube, from, to, direction))| CubeSlid {
+                home: cube.home(),
+                from,
+                to,
+                direction,
+                step,
+                lead: i == 0,
+            })
+            .collect()
+    }
+}
+
+/// A board meeting `goal`, empty at `hole` unless its belts turn with no hole at all.
+fn fresh(goal: &Goal, hole: Pos3D, puzzle: Puzzle) -> Board {
+    match puzzle {
+        Puzzle::Slide => goal.solved(&[hole]),
+        Puzzle::Belts => goal.solved(&[]),
+    }
+}
+
+/// The goal of `shape` on a box of `spaces`, falling back to every cube at home.
+fn fit_goal(shape: GoalShape, spaces: Size3D) -> (GoalShape, Goal) {
+    match shape.goal(spaces) {
+        Some(goal) => (shape, goal),
+        None => {
+            warn!("The {} goal does not fit {:?}", shape.name(), spaces);
+            (GoalShape::Identity, Goal::identity(spaces))
+        }
+    }
+}
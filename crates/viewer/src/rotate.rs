@@ -1,4 +1,12 @@
+use crate::animation::{unit_vec, Easing};
+use crate::clock::Clock;
+use crate::components::PuzzleCamera;
+use crate::net::{self, NetView};
+use crate::play::PlayMode;
+use crate::resources::{BoardResource, CubesResource};
+use alignment::model::{Direction3D, Pos3D};
 use bevy::input::mouse::*;
+use bevy::input::touch::Touch;
 use bevy::prelude::*;
 use bevy::render::camera::PerspectiveProjection;
 
@@ -21,9 +29,12 @@ impl Default for PanOrbitCamera {
     }
 }
 
-/// Pan the camera with middle mouse click, zoom with scroll wheel, orbit with right mouse click.
+/// Pan the camera with middle mouse click, zoom with scroll wheel, orbit with left mouse click.
+///
+/// On touch screens one finger orbits, and two fingers pan, pinch to zoom and twist to turn.
 pub fn action(
     windows: Res<Windows>,
+    touches: Res<Touches>,
     mut ev_motion: EventReader<MouseMotion>,
     mut ev_scroll: EventReader<MouseWheel>,
     input_mouse: Res<Input<MouseButton>>,
@@ -31,15 +42,22 @@ pub fn action(
 ) {
     // change input mapping for orbit and panning here
     let orbit_button = MouseButton::Left;
+    let pan_button = MouseButton::Middle;
 
+    let mut pan = Vec2::ZERO;
     let mut rotation_move = Vec2::ZERO;
     let mut scroll = 0.0;
+    let mut twist = 0.0;
     let mut orbit_button_changed = false;
 
     if input_mouse.pressed(orbit_button) {
         for ev in ev_motion.iter() {
             rotation_move += ev.delta;
         }
+    } else if input_mouse.pressed(pan_button) {
+        for ev in ev_motion.iter() {
+            pan += ev.delta;
+        }
     }
     for ev in ev_scroll.iter() {
         scroll += ev.y / 100.0;
@@ -48,7 +66,26 @@ pub fn action(
         orbit_button_changed = true;
     }
 
-    for (mut pan_orbit, mut transform, _) in query.iter_mut() {
+    let fingers: Vec<&Touch> = touches.iter().collect();
+    match fingers[..] {
+        [finger] => rotation_move += finger.delta(),
+        [a, b] => {
+            let before = b.previous_position() - a.previous_position();
+            let after = b.position() - a.position();
+            pan += (a.delta() + b.delta()) / 2.0;
+            if before.length() > 0.0 {
+                scroll += (after.length() / before.length() - 1.0) * 2.0;
+            }
+            twist += before.angle_between(after);
+        }
+        _ => {}
+    }
+    if touches.iter_just_pressed().next().is_some() || touches.iter_just_released().next().is_some()
+    {
+        orbit_button_changed = true;
+    }
+
+    for (mut pan_orbit, mut transform, projection) in query.iter_mut() {
         if orbit_button_changed {
             // only check for upside down when orbiting started or ended this frame
             // if the camera is "upside" down, panning horizontally would be inverted, so invert the input to make it correct
@@ -73,7 +110,24 @@ pub fn action(
             let pitch = Quat::from_rotation_x(-delta_y);
             transform.rotation = yaw * transform.rotation; // rotate around global y axis
             transform.rotation *= pitch; // rotate around local x axis
-        } else if scroll.abs() > 0.0 {
+        }
+        if twist.is_finite() && twist.abs() > 0.0 {
+            any = true;
+            transform.rotation = Quat::from_rotation_y(-twist) * transform.rotation;
+        }
+        if pan.length_squared() > 0.0 {
+            any = true;
+            // make panning distance independent of resolution and FOV,
+            let window = get_primary_window_size(&windows);
+            pan *= Vec2::new(projection.fov * projection.aspect_ratio, projection.fov) / window;
+            // translate by local axes
+            let right = transform.rotation * Vec3::X * -pan.x;
+            let up = transform.rotation * Vec3::Y * pan.y;
+            // make panning proportional to distance away from focus point
+            let translation = (right + up) * pan_orbit.radius;
+            pan_orbit.focus += translation;
+        }
+        if scroll.abs() > 0.0 {
             any = true;
             pan_orbit.radius -= scroll * pan_orbit.radius * 0.2;
             // dont allow zoom to reach zero or you get stuck
@@ -81,22 +135,207 @@ pub fn action(
         }
 
         if any {
-            // emulating parent/child to make the yaw/y-axis rotation behave like a turntable
-            // parent = x and y rotation
-            // child = z-offset
-            let rot_matrix = Mat3::from_quat(transform.rotation);
-            transform.translation =
-                pan_orbit.focus + rot_matrix.mul_vec3(Vec3::new(0.0, 0.0, pan_orbit.radius));
+            place(&pan_orbit, &mut transform);
+        }
+    }
+}
+
+/// Moves the camera back onto its orbit after the focus, radius or rotation changed.
+fn place(pan_orbit: &PanOrbitCamera, transform: &mut Transform) {
+    // emulating parent/child to make the yaw/y-axis rotation behave like a turntable
+    // parent = x and y rotation
+    // child = z-offset
+    let rot_matrix = Mat3::from_quat(transform.rotation);
+    transform.translation =
+        pan_orbit.focus + rot_matrix.mul_vec3(Vec3::new(0.0, 0.0, pan_orbit.radius));
+}
+
+/// Centres the board and backs off until all of it fits in view, whenever it is built or resized
+/// or the net is opened or closed.
+pub fn frame(
+    resource: Res<CubesResource>,
+    net: Res<NetView>,
+    mut was_open: Local<bool>,
+    mut query: Query<(&mut PanOrbitCamera, &mut Transform, &PerspectiveProjection)>,
+) {
+    if !resource.is_changed() && *was_open == net.open {
+        return;
+    }
+    *was_open = net.open;
+    let (focus, radius) = if net.open {
+        net::bounds(&resource)
+    } else {
+        bounds(&resource)
+    };
+    for (mut pan_orbit, mut transform, projection) in query.iter_mut() {
+        pan_orbit.focus = focus;
+        pan_orbit.radius = fit_distance(radius, projection);
+        place(&pan_orbit, &mut transform);
+    }
+}
+
+/// Centre and radius of a sphere holding every cube.
+pub fn bounds(resource: &CubesResource) -> (Vec3, f32) {
+    let spaces = resource.spaces;
+    let far = Pos3D::new(spaces.x() - 1, spaces.y() - 1, spaces.z() - 1);
+    let min = resource.calc_center(Pos3D::new(0, 0, 0));
+    let max = resource.calc_center(far);
+    let focus = (min + max) / 2.0;
+    let radius = (max - min).length() / 2.0 + resource.cube_size * 3.0_f32.sqrt() / 2.0;
+    (focus, radius)
+}
+
+/// How far from its centre the camera must be for a sphere of `radius` to fill the view.
+pub fn fit_distance(radius: f32, projection: &PerspectiveProjection) -> f32 {
+    const MARGIN: f32 = 1.1;
+
+    let half_fov_y = projection.fov / 2.0;
+    let half_fov_x = (half_fov_y.tan() * projection.aspect_ratio).atan();
+    MARGIN * radius / half_fov_y.min(half_fov_x).sin()
+}
+
+/// Which face the camera looks at squarely, and whether it should chase the hole there.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FaceFocus {
+    pub face: Option<Direction3D>,
+    pub follow_hole: bool,
+}
+
+/// A swing of the camera round its focus, from one orientation to another.
+#[derive(Debug, Clone, Copy, Component)]
+pub struct CameraTween {
+    from: Quat,
+    to: Quat,
+    elapsed: f32,
+}
+
+impl CameraTween {
+    const DURATION: f32 = 0.6;
+}
+
+/// Orientation that puts the camera straight in front of face `d`, with the box upright where
+/// that makes sense.
+pub fn face_rotation(d: Direction3D) -> Quat {
+    let up = match d {
+        Direction3D::YNega => Vec3::Z,
+        Direction3D::YPosi => -Vec3::Z,
+        _ => Vec3::Y,
+    };
+    Transform::from_translation(unit_vec(d))
+        .looking_at(Vec3::ZERO, up)
+        .rotation
+}
+
+/// Keys 1 to 6 swing round to a face, in `Direction3D` order, and F toggles following the hole.
+pub fn snap_keys(keys: Res<Input<KeyCode>>, mut focus: ResMut<FaceFocus>) {
+    const FACE_KEYS: [(KeyCode, Direction3D); 6] = [
+        (KeyCode::Key1, Direction3D::XNega),
+        (KeyCode::Key2, Direction3D::XPosi),
+        (KeyCode::Key3, Direction3D::YNega),
+        (KeyCode::Key4, Direction3D::YPosi),
+        (KeyCode::Key5, Direction3D::ZNega),
+        (KeyCode::Key6, Direction3D::ZPosi),
+    ];
+
+    if let Some((_, d)) = FACE_KEYS.iter().find(|(key, _)| keys.just_pressed(*key)) {
+        focus.face = Some(*d);
+    }
+    if keys.just_pressed(KeyCode::F) {
+        focus.follow_hole = !focus.follow_hole;
+        info!("Follow the hole: {}", focus.follow_hole);
+    }
+}
+
+/// While playing with follow on, turns to whichever face the hole moved onto, staying put while
+/// the hole is still on the face in view.
+pub fn follow_hole(
+    mode: Res<PlayMode>,
+    board: Res<BoardResource>,
+    net: Res<NetView>,
+    mut focus: ResMut<FaceFocus>,
+    query: Query<&Transform, With<PanOrbitCamera>>,
+) {
+    // The opened net already shows every face.
+    if !focus.follow_hole || *mode != PlayMode::Manual || net.open {
+        return;
+    }
+    let faces = match board.hole() {
+        Some(hole) => hole.get_faces(board.board.size()),
+        None => return,
+    };
+    if focus.face.is_some_and(|face| faces.contains(&face)) {
+        return;
+    }
+    // Of the faces the hole sits on, pick the one already turned most toward the camera.
+    let view = match query.iter().next() {
+        Some(transform) => transform.rotation * Vec3::Z,
+        None => return,
+    };
+    let nearest = faces.into_iter().max_by(|a, b| {
+        let (a, b) = (unit_vec(*a).dot(view), unit_vec(*b).dot(view));
+        a.partial_cmp(&b).unwrap_or(std::cmp::Ordering::Equal)
+    });
+    if nearest.is_some() {
+        focus.face = nearest;
+    }
+}
+
+/// Starts a swing whenever the focused face changes, and plays it out; any drag or touch
+/// cancels it.
+pub fn focus_face(
+    mut commands: Commands,
+    time: Res<Clock>,
+    focus: Res<FaceFocus>,
+    input_mouse: Res<Input<MouseButton>>,
+    touches: Res<Touches>,
+    mut query: Query<(
+        Entity,
+        &mut PanOrbitCamera,
+        &mut Transform,
+        Option<&mut CameraTween>,
+    )>,
+) {
+    let grabbed = input_mouse.pressed(MouseButton::Left)
+        || input_mouse.pressed(MouseButton::Middle)
+        || touches.iter().next().is_some();
+
+    for (entity, mut pan_orbit, mut transform, tween) in query.iter_mut() {
+        if focus.is_changed() {
+            if let Some(face) = focus.face {
+                commands.entity(entity).insert(CameraTween {
+                    from: transform.rotation,
+                    to: face_rotation(face),
+                    elapsed: 0.0,
+                });
+            }
+            continue;
+        }
+        let mut tween = match tween {
+            Some(tween) => tween,
+            None => continue,
+        };
+        if grabbed {
+            commands.entity(entity).remove::<CameraTween>();
+            continue;
+        }
+
+        tween.elapsed += time.delta_seconds();
+        let progress = Easing::CubicInOut.apply(tween.elapsed / CameraTween::DURATION);
+        transform.rotation = tween.from.slerp(tween.to, progress);
+        place(&pan_orbit, &mut transform);
+        if tween.elapsed >= CameraTween::DURATION {
+            pan_orbit.upside_down = (transform.rotation * Vec3::Y).y <= 0.0;
+            commands.entity(entity).remove::<CameraTween>();
         }
     }
 }
 
 fn get_primary_window_size(windows: &Res<Windows>) -> Vec2 {
     let window = windows.get_primary().unwrap();
-    Vec2::new(window.width() as f32, window.height() as f32)
+    Vec2::new(window.width(), window.height())
 }
 
-/// Spawn a camera like this
+/// Spawn a camera like this; `frame` moves it out to fit the board.
 pub fn setup(mut commands: Commands) {
     let translation = Vec3::new(-4.0, 5.5, 10.0);
     let radius = translation.length();
@@ -109,5 +348,6 @@ pub fn setup(mut commands: Commands) {
         .insert(PanOrbitCamera {
             radius,
             ..Default::default()
-        });
+        })
+        .insert(PuzzleCamera);
 }
@@ -0,0 +1,359 @@
+use crate::belts::Puzzle;
+use crate::clock::Clock;
+use crate::config::{self, ViewerConfig};
+use crate::events::{CubeSlid, Step};
+use crate::hud::PlayStats;
+use crate::play::PlayMode;
+use crate::resources::{BoardResource, CubesResource};
+use crate::ui::UiFont;
+use alignment::board::Board;
+use alignment::format::{Game, TimedMove};
+use bevy::prelude::*;
+
+/// Seconds between saves while the player thinks without sliding.
+const AUTOSAVE_SECONDS: f64 = 10.0;
+
+/// The manual game being played, written to storage after every slide so it survives the window
+/// or tab being closed.
+pub struct GameLog {
+    /// Everything played since manual play began on the current board; none during the demo.
+    pub game: Option<Game>,
+    /// The game left over from the last session, until it is resumed or saved over.
+    pub offer: Option<Game>,
+    resumed: bool,
+    dirty: bool,
+}
+
+impl FromWorld for GameLog {
+    fn from_world(world: &mut World) -> Self {
+        let config = world.get_resource_or_insert_with(ViewerConfig::default);
+        let offer = config
+            .save
+            .as_deref()
+            .and_then(load)
+            .and_then(|text| Game::parse(&text))
+            .and_then(playable);
+        if offer.is_some() {
+            info!("A saved game is waiting; press R to resume it");
+        }
+        GameLog {
+            game: None,
+            offer,
+            resumed: false,
+            dirty: false,
+        }
+    }
+}
+
+impl GameLog {
+    /// Carries on with `game`, whose current board is queued to be set up.
+    pub fn resume(&mut self, game: Game) {
+        self.game = Some(game);
+        self.resumed = true;
+    }
+}
+
+/// `game`, unless its box is smaller or larger than the viewer builds, which a game from outside,
+/// edited by hand or cut short, may well be.
+pub fn playable(game: Game) -> Option<Game> {
+    let size = game.start.size();
+    if config::clamp_spaces(size) == size {
+        return Some(game);
+    }
+    warn!(
+        "Ignoring a game on a {}x{}x{} box; sides run from {} to {}",
+        size.x(),
+        size.y(),
+        size.z(),
+        ViewerConfig::MIN_SPACE,
+        ViewerConfig::MAX_SPACE,
+    );
+    None
+}
+
+/// Sets up the offered game with R, in manual play.
+pub fn resume(
+    keys: Res<Input<KeyCode>>,
+    mut log: ResMut<GameLog>,
+    mut config: ResMut<ViewerConfig>,
+    mut resource: ResMut<CubesResource>,
+    mut board: ResMut<BoardResource>,
+    mut mode: ResMut<PlayMode>,
+) {
+    if !keys.just_pressed(KeyCode::R) {
+        return;
+    }
+    if let Some(game) = log.offer.take() {
+        info!("Resuming a game of {} moves", game.moves.len());
+        *mode = PlayMode::Manual;
+        queue(
+            &game,
+            game.current(),
+            &mut config,
+            &mut resource,
+            &mut board,
+        );
+        log.resume(game);
+    }
+}
+
+/// Sets up `board`, a board of `game`, in place of the one in play, along with the game's goal.
+///
+/// Games are kept of the sliding puzzle only, so the belts make way for it.
+pub fn queue(
+    game: &Game,
+    board: Board,
+    config: &mut ViewerConfig,
+    resource: &mut CubesResource,
+    target: &mut BoardResource,
+) {
+    let spaces = board.size();
+    // Touching the resource rebuilds the board even when its size stays the same.
+    config.spaces = spaces;
+    config.goal = game.goal;
+    config.puzzle = Puzzle::Slide;
+    resource.spaces = spaces;
+    resource.goal = game.goal;
+    resource.puzzle = Puzzle::Slide;
+    target.queued = Some((board, game.seed));
+}
+
+/// Follows the slides of manual play, starting a new game whenever manual play finds none.
+///
+/// Belt turns have no place in a game's text, so nothing is kept while the belts are played.
+pub fn record(
+    mode: Res<PlayMode>,
+    resource: Res<CubesResource>,
+    board: Res<BoardResource>,
+    time: Res<Clock>,
+    mut stats: ResMut<PlayStats>,
+    mut log: ResMut<GameLog>,
+    mut events: EventReader<CubeSlid>,
+) {
+    let now = time.seconds_since_startup();
+    // Whole milliseconds keep the saved times short.
+    let at = (stats.elapsed(now) * 1000.0).round() / 1000.0;
+    let moves: Vec<TimedMove> = events
+        .iter()
+        .filter_map(|slid| match slid.step {
+            Step::Move(mv) if slid.lead => Some(TimedMove::new(mv, at)),
+            _ => None,
+        })
+        .collect();
+    if *mode != PlayMode::Manual || resource.puzzle != Puzzle::Slide {
+        if log.game.is_some() {
+            log.game = None;
+        }
+        return;
+    }
+
+    let log = &mut *log;
+    if log.resumed {
+        // The moves and time played before count toward this session's stats.
+        log.resumed = false;
+        if let Some(game) = &log.game {
+            stats.moves = game.moves.len() as u32;
+            stats.started = Some(now - game.seconds);
+        }
+        return;
+    }
+    if resource.is_changed() {
+        // A new board starts a new game.
+        log.game = None;
+    }
+    match &mut log.game {
+        Some(game) => {
+            if !moves.is_empty() {
+                game.moves.extend(moves);
+                log.dirty = true;
+            }
+        }
+        None => {
+            let mut game = Game::new(board.board.clone(), board.seed);
+            game.goal = board.shape;
+            log.game = Some(game);
+        }
+    }
+}
+
+/// Keeps the game in storage up to date, and clears it once the puzzle is solved.
+pub fn autosave(
+    config: Res<ViewerConfig>,
+    board: Res<BoardResource>,
+    stats: Res<PlayStats>,
+    time: Res<Clock>,
+    mut log: ResMut<GameLog>,
+    mut saved_at: Local<f64>,
+) {
+    let slot = match &config.save {
+        Some(slot) => slot,
+        None => return,
+    };
+    let now = time.seconds_since_startup();
+    let solved = board.is_solved();
+    if !log.dirty && (solved || now - *saved_at < AUTOSAVE_SECONDS) {
+        return;
+    }
+
+    let log = &mut *log;
+    let game = match &mut log.game {
+        // A game without moves is no different from the board it would start from.
+        Some(game) if !game.moves.is_empty() => game,
+        _ => return,
+    };
+    log.dirty = false;
+    log.offer = None;
+    *saved_at = now;
+    if solved {
+        erase(slot);
+    } else {
+        game.seconds = stats.elapsed(now);
+        store(slot, &game.to_text());
+    }
+}
+
+#[derive(Debug, Clone, Copy, Component)]
+pub struct ResumePrompt;
+
+pub fn setup(mut commands: Commands, font: Res<UiFont>) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    bottom: Val::Px(10.0),
+                    left: Val::Px(10.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section("", font.style(20.0), Default::default()),
+            ..Default::default()
+        })
+        .insert(ResumePrompt);
+}
+
+/// Offers the saved game for as long as it can be resumed.
+pub fn prompt(log: Res<GameLog>, mut query: Query<&mut Text, With<ResumePrompt>>) {
+    if !log.is_changed() {
+        return;
+    }
+    let value = match &log.offer {
+        Some(game) => {
+            let size = game.start.size();
+            format!(
+                "Resume {}x{}x{}: {} moves, {:02}:{:04.1} (R)",
+                size.x(),
+                size.y(),
+                size.z(),
+                game.moves.len(),
+                (game.seconds / 60.0) as u32,
+                game.seconds % 60.0,
+            )
+        }
+        None => String::new(),
+    };
+    for mut text in query.iter_mut() {
+        text.sections[0].value = value.clone();
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load(slot: &str) -> Option<String> {
+    std::fs::read_to_string(slot).ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn store(slot: &str, text: &str) {
+    if let Err(e) = std::fs::write(slot, text) {
+        warn!("Could not save the game to {}: {}", slot, e);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn erase(slot: &str) {
+    // The file is missing if the game was solved before its first save.
+    let _ = std::fs::remove_file(slot);
+}
+
+#[cfg(target_arch = "wasm32")]
+fn storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn load(slot: &str) -> Option<String> {
+    storage()?.get_item(slot).ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn store(slot: &str, text: &str) {
+    let stored = storage().map(|storage| storage.set_item(slot, text).is_ok());
+    if stored != Some(true) {
+        warn!("Could not save the game to local storage");
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn erase(slot: &str) {
+    if let Some(storage) = storage() {
+        let _ = storage.remove_item(slot);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::harness::Harness;
+    use alignment::model::{Pos3D, Size3D};
+
+    #[test]
+    fn games_are_recorded_and_resumed() {
+        let mut harness = Harness::manual();
+        harness.advance(0.1);
+        let start = harness.board().board.clone();
+        let mv = start.moves()[0];
+        assert!(harness.slide(mv));
+        harness.run_for(2.0, 0.5);
+
+        let mut game = harness.resource::<GameLog>().game.clone().unwrap();
+        assert_eq!(game.start, start);
+        assert_eq!(game.moves, vec![TimedMove::new(mv, 0.0)]);
+        assert_eq!(game.current(), harness.board().board);
+
+        // Resuming on a board of another size rebuilds it as it was left.
+        game.seconds = 30.0;
+        let saved = Game::parse(&game.to_text()).unwrap();
+        harness.resource_mut::<ViewerConfig>().spaces = Size3D::new(2, 3, 4);
+        harness.advance(0.1);
+        harness.resource_mut::<GameLog>().offer = Some(saved);
+        harness.tap(KeyCode::R);
+        assert_eq!(harness.board().board, game.current());
+        assert_eq!(harness.bodies().len(), game.current().parts().len());
+        assert_eq!(harness.resource::<ViewerConfig>().spaces, start.size());
+        let stats = harness.resource::<PlayStats>().clone();
+        assert_eq!(stats.moves, 1);
+        let now = harness.resource::<Clock>().seconds_since_startup();
+        assert!((stats.elapsed(now) - 30.0).abs() < 1e-6);
+
+        harness.tap(KeyCode::Space);
+        assert!(harness.resource::<GameLog>().game.is_none());
+    }
+
+    #[test]
+    fn saves_of_oversized_boxes_are_not_offered() {
+        let slot = std::env::temp_dir().join(format!("oversized-{}.save", std::process::id()));
+        let slot = slot.to_str().unwrap().to_string();
+        let board = Board::with_hole(Size3D::new(13, 2, 2), Pos3D::new(0, 0, 0));
+        store(&slot, &Game::new(board, 1).to_text());
+
+        let mut world = World::new();
+        world.insert_resource(ViewerConfig {
+            save: Some(slot.clone()),
+            ..Default::default()
+        });
+        let log = GameLog::from_world(&mut world);
+        erase(&slot);
+        assert!(log.offer.is_none());
+    }
+}
@@ -0,0 +1,290 @@
+use crate::animation::AnimationSettings;
+use crate::config::{clamp_spaces, ViewerConfig};
+use crate::faces::FaceArt;
+use crate::resources::CubesResource;
+use crate::ui::UiFont;
+use alignment::model::Size3D;
+use bevy::prelude::*;
+use textures::palette::{Palette, Preset};
+
+const BUTTON_NORMAL: Color = Color::rgb(0.25, 0.25, 0.25);
+const BUTTON_HOVERED: Color = Color::rgb(0.35, 0.35, 0.35);
+const BUTTON_PRESSED: Color = Color::rgb(0.45, 0.55, 0.45);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Setting {
+    SizeX,
+    SizeY,
+    SizeZ,
+    CubeSize,
+    Goal,
+    Puzzle,
+    Speed,
+    Theme,
+    Palette,
+    Cues,
+}
+
+impl Setting {
+    const ALL: [Setting; 10] = [
+        Setting::SizeX,
+        Setting::SizeY,
+        Setting::SizeZ,
+        Setting::CubeSize,
+        Setting::Goal,
+        Setting::Puzzle,
+        Setting::Speed,
+        Setting::Theme,
+        Setting::Palette,
+        Setting::Cues,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            Setting::SizeX => "X",
+            Setting::SizeY => "Y",
+            Setting::SizeZ => "Z",
+            Setting::CubeSize => "Cube",
+            Setting::Goal => "Goal",
+            Setting::Puzzle => "Puzzle",
+            Setting::Speed => "Speed",
+            Setting::Theme => "Theme",
+            Setting::Palette => "Colour",
+            Setting::Cues => "Cues",
+        }
+    }
+
+    fn value(self, config: &ViewerConfig) -> String {
+        match self {
+            Setting::SizeX => config.spaces.x().to_string(),
+            Setting::SizeY => config.spaces.y().to_string(),
+            Setting::SizeZ => config.spaces.z().to_string(),
+            Setting::CubeSize => format!("{:.2}", config.cube_size),
+            Setting::Goal => config.goal.name(),
+            Setting::Puzzle => config.puzzle.name().to_string(),
+            Setting::Speed => format!("{:.2}", config.speed),
+            Setting::Theme => format!("{:?}", config.face_theme()),
+            Setting::Palette => config
+                .palette
+                .preset()
+                .map_or("custom", Preset::name)
+                .to_string(),
+            Setting::Cues => if config.palette.cues { "on" } else { "off" }.to_string(),
+        }
+    }
+
+    fn step(self, config: &mut ViewerConfig, up: bool) {
+        let space = |v: u8| if up { v.saturating_add(1) } else { v - 1 };
+        let spaces = config.spaces;
+        match self {
+            Setting::SizeX => {
+                config.spaces = clamp_spaces(Size3D::new(space(spaces.x()), spaces.y(), spaces.z()))
+            }
+            Setting::SizeY => {
+                config.spaces = clamp_spaces(Size3D::new(spaces.x(), space(spaces.y()), spaces.z()))
+            }
+            Setting::SizeZ => {
+                config.spaces = clamp_spaces(Size3D::new(spaces.x(), spaces.y(), space(spaces.z())))
+            }
+            Setting::CubeSize => {
+                let v = config.cube_size + if up { 0.25 } else { -0.25 };
+                config.cube_size =
+                    v.clamp(ViewerConfig::MIN_CUBE_SIZE, ViewerConfig::MAX_CUBE_SIZE);
+            }
+            Setting::Goal => {
+                config.goal = if up {
+                    config.goal.next()
+                } else {
+                    config.goal.prev()
+                }
+            }
+            // Two puzzles: either way leads to the other.
+            Setting::Puzzle => config.puzzle = config.puzzle.next(),
+            Setting::Speed => {
+                let v = config.speed * if up { 1.5 } else { 1.0 / 1.5 };
+                config.speed = v.clamp(ViewerConfig::MIN_SPEED, ViewerConfig::MAX_SPEED);
+            }
+            Setting::Theme => {
+                let theme = config.face_theme();
+                config.theme = Some(if up { theme.next() } else { theme.prev() });
+            }
+            Setting::Palette => {
+                // A custom palette steps onto the presets, starting from the first.
+                let preset = match config.palette.preset() {
+                    Some(preset) if up => preset.next(),
+                    Some(preset) => preset.prev(),
+                    None => Preset::Classic,
+                };
+                config.palette = Palette {
+                    cues: config.palette.cues || preset.palette().cues,
+                    ..preset.palette()
+                };
+            }
+            Setting::Cues => config.palette.cues = !config.palette.cues,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Component)]
+pub struct SettingsPanel;
+
+#[derive(Debug, Clone, Copy, Component)]
+pub struct SettingButton {
+    pub setting: Setting,
+    pub up: bool,
+}
+
+#[derive(Debug, Clone, Copy, Component)]
+pub struct SettingValue(pub Setting);
+
+pub fn setup(mut commands: Commands, font: Res<UiFont>, config: Res<ViewerConfig>) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(10.0),
+                    right: Val::Px(10.0),
+                    ..Default::default()
+                },
+                flex_direction: FlexDirection::ColumnReverse,
+                padding: Rect::all(Val::Px(6.0)),
+                ..Default::default()
+            },
+            color: Color::rgba(0.0, 0.0, 0.0, 0.5).into(),
+            ..Default::default()
+        })
+        .insert(SettingsPanel)
+        .with_children(|panel| {
+            Setting::ALL.into_iter().for_each(|setting| {
+                panel
+                    .spawn_bundle(NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Row,
+                            align_items: AlignItems::Center,
+                            margin: Rect::all(Val::Px(2.0)),
+                            ..Default::default()
+                        },
+                        color: Color::NONE.into(),
+                        ..Default::default()
+                    })
+                    .with_children(|row| {
+                        spawn_text(row, &font, format!("{:<6}", setting.label()));
+                        spawn_button(row, &font, setting, false);
+                        row.spawn_bundle(text_bundle(&font, setting.value(&config)))
+                            .insert(SettingValue(setting));
+                        spawn_button(row, &font, setting, true);
+                    });
+            });
+        });
+}
+
+fn text_bundle(font: &UiFont, value: String) -> TextBundle {
+    TextBundle {
+        style: Style {
+            margin: Rect::all(Val::Px(4.0)),
+            ..Default::default()
+        },
+        text: Text::with_section(value, font.style(18.0), Default::default()),
+        ..Default::default()
+    }
+}
+
+fn spawn_text(parent: &mut ChildBuilder, font: &UiFont, value: String) {
+    parent.spawn_bundle(text_bundle(font, value));
+}
+
+fn spawn_button(parent: &mut ChildBuilder, font: &UiFont, setting: Setting, up: bool) {
+    parent
+        .spawn_bundle(ButtonBundle {
+            style: Style {
+                size: Size::new(Val::Px(24.0), Val::Px(24.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            color: BUTTON_NORMAL.into(),
+            ..Default::default()
+        })
+        .insert(SettingButton { setting, up })
+        .with_children(|button| {
+            let sign = if up { "+" } else { "-" };
+            button.spawn_bundle(TextBundle {
+                text: Text::with_section(sign, font.style(18.0), Default::default()),
+                ..Default::default()
+            });
+        });
+}
+
+pub fn press(
+    mut config: ResMut<ViewerConfig>,
+    mut query: Query<(&Interaction, &SettingButton, &mut UiColor), Changed<Interaction>>,
+) {
+    for (interaction, button, mut color) in query.iter_mut() {
+        *color = match *interaction {
+            Interaction::Clicked => {
+                button.setting.step(&mut config, button.up);
+                BUTTON_PRESSED.into()
+            }
+            Interaction::Hovered => BUTTON_HOVERED.into(),
+            Interaction::None => BUTTON_NORMAL.into(),
+        };
+    }
+}
+
+pub fn refresh(config: Res<ViewerConfig>, mut query: Query<(&SettingValue, &mut Text)>) {
+    if !config.is_changed() {
+        return;
+    }
+    for (value, mut text) in query.iter_mut() {
+        text.sections[0].value = value.0.value(&config);
+    }
+}
+
+/// Shows or hides the panel with Tab.
+pub fn toggle(keys: Res<Input<KeyCode>>, mut query: Query<&mut Style, With<SettingsPanel>>) {
+    if keys.just_pressed(KeyCode::Tab) {
+        for mut style in query.iter_mut() {
+            style.display = match style.display {
+                Display::None => Display::Flex,
+                Display::Flex => Display::None,
+            };
+        }
+    }
+}
+
+/// Pushes the chosen options into the resources the board and animation read from.
+pub fn apply(
+    config: Res<ViewerConfig>,
+    mut resource: ResMut<CubesResource>,
+    mut animation: ResMut<AnimationSettings>,
+    mut art: ResMut<FaceArt>,
+) {
+    if !config.is_changed() {
+        return;
+    }
+    if art.theme != config.face_theme() {
+        art.theme = config.face_theme();
+    }
+    if art.palette != config.palette {
+        art.palette = config.palette;
+    }
+    if resource.spaces != config.spaces
+        || resource.cube_size != config.cube_size
+        || resource.goal != config.goal
+        || resource.puzzle != config.puzzle
+    {
+        info!(
+            "Rebuilding board: {:?} x {} toward {}, {}",
+            config.spaces,
+            config.cube_size,
+            config.goal.name(),
+            config.puzzle.name()
+        );
+        resource.spaces = config.spaces;
+        resource.cube_size = config.cube_size;
+        resource.goal = config.goal;
+        resource.puzzle = config.puzzle;
+    }
+    animation.duration = 1.0 / config.speed;
+}
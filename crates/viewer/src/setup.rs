@@ -1,36 +1,17 @@
-use crate::move_cubes;
-use crate::put_cubes;
-use crate::resources::*;
-use crate::rotate;
+use crate::config::ViewerConfig;
+use crate::plugin::SlidePuzzlePlugin;
 use bevy::prelude::*;
 
+/// The standalone viewer: a window sized from the options, with the puzzle owning everything in it.
 pub fn run() {
-    let mut app = App::new();
+    let config = ViewerConfig::from_env();
 
-    app.add_plugins(DefaultPlugins);
-
-    app.init_resource::<CubesResource>();
-    app.add_startup_system(setup);
-    app.add_startup_system(rotate::setup);
-    app.add_startup_system(put_cubes::setup);
-    app.add_startup_system(move_cubes::setup);
-
-    app.add_system(rotate::action);
-    app.add_system(move_cubes::action);
-
-    app.run();
-}
-
-fn setup(mut commands: Commands, mut windows: ResMut<Windows>) {
-    let window = windows.get_primary_mut().unwrap();
-    window.set_resolution(800.0, 800.0);
-    // light
-    commands.spawn_bundle(PointLightBundle {
-        transform: Transform::from_translation(Vec3::new(4.0, 8.0, 4.0)),
-        ..Default::default()
-    });
-    commands.spawn_bundle(PointLightBundle {
-        transform: Transform::from_translation(Vec3::new(-10.0, -20.0, -10.0)),
-        ..Default::default()
-    });
+    App::new()
+        .insert_resource(config.window())
+        .add_plugins(DefaultPlugins)
+        .add_plugin(SlidePuzzlePlugin {
+            config,
+            ..Default::default()
+        })
+        .run();
 }
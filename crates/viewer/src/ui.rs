@@ -0,0 +1,30 @@
+use bevy::prelude::*;
+
+/// The font every overlay uses, embedded so native and wasm builds need no asset files.
+pub struct UiFont(pub Handle<Font>);
+
+impl FromWorld for UiFont {
+    fn from_world(world: &mut World) -> Self {
+        let font =
+            Font::try_from_bytes(include_bytes!("../assets/fonts/DejaVuSansMono.ttf").to_vec())
+                .expect("embedded font must be valid");
+        let mut fonts = world
+            .get_resource_mut::<Assets<Font>>()
+            .expect("Assets<Font> must be initialized first");
+        UiFont(fonts.add(font))
+    }
+}
+
+impl UiFont {
+    pub fn style(&self, font_size: f32) -> TextStyle {
+        TextStyle {
+            font: self.0.clone(),
+            font_size,
+            color: Color::WHITE,
+        }
+    }
+}
+
+pub fn setup(mut commands: Commands) {
+    commands.spawn_bundle(UiCameraBundle::default());
+}
@@ -0,0 +1,262 @@
+use crate::animation::{unit_vec, CubeAnimation};
+use crate::clock::Clock;
+use crate::components::*;
+use crate::events::{CubeSlid, PuzzleSolved};
+use crate::play::PlayMode;
+use crate::replay::Replay;
+use crate::resources::{BoardResource, CubesResource};
+use crate::save::GameLog;
+use crate::ui::UiFont;
+use alignment::model::{Pos3D, Size3D};
+use alignment::solver::{Metric, Solver};
+use bevy::prelude::*;
+use std::collections::HashSet;
+use std::f32::consts::PI;
+
+/// Boards the search for the shortest solution may expand before the optimum is left unknown.
+///
+/// The search runs within the frame that sees the solve, so only short games get an optimum.
+const OPTIMAL_BUDGET: usize = 2_000;
+
+/// How the last game was solved.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Summary {
+    pub moves: u32,
+    pub seconds: f64,
+    /// The fewest moves the game could have been solved in, if the search for them finished.
+    pub optimal: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Phase {
+    Idle,
+    /// Solved, until the last slide has been drawn.
+    Waiting,
+    /// Seconds into the wave of hops.
+    Hopping(f32),
+    Done,
+}
+
+/// The celebration after a solve, and the summary shown once it is over.
+pub struct Victory {
+    pub phase: Phase,
+    pub summary: Option<Summary>,
+}
+
+impl Default for Victory {
+    fn default() -> Self {
+        Victory {
+            phase: Phase::Idle,
+            summary: None,
+        }
+    }
+}
+
+impl Victory {
+    /// Seconds one cube spends in the air.
+    const HOP: f32 = 0.5;
+    /// How much later a cube one slide further from the hole takes off.
+    const STAGGER: f32 = 0.06;
+    /// Peak of a hop in cube sizes.
+    const HEIGHT: f32 = 0.6;
+}
+
+/// Sends a wave of hops across the box from the hole once a game is solved.
+///
+/// Any slide, a new board or another play mode calls it off and puts the cubes back.
+#[allow(clippy::too_many_arguments)]
+pub fn celebrate(
+    mode: Res<PlayMode>,
+    resource: Res<CubesResource>,
+    board: Res<BoardResource>,
+    animation: Res<CubeAnimation>,
+    log: Res<GameLog>,
+    replay: Res<Replay>,
+    time: Res<Clock>,
+    mut victory: ResMut<Victory>,
+    mut solved: EventReader<PuzzleSolved>,
+    mut slid: EventReader<CubeSlid>,
+    mut query: Query<&mut Transform, With<CubeHome>>,
+) {
+    let moved: HashSet<Pos3D> = slid.iter().map(|slid| slid.home).collect();
+    let interrupted = !moved.is_empty() || mode.is_changed() || resource.is_changed();
+    if interrupted && victory.phase != Phase::Idle {
+        if let Phase::Hopping(_) = victory.phase {
+            // Cubes that have just moved are the animation's to place.
+            for (pos, cube) in board.board.parts() {
+                if moved.contains(&cube.home()) {
+                    continue;
+                }
+                if let Some(entity) = board.entities.get(&cube.home()) {
+                    if let Ok(mut tr) = query.get_mut(*entity) {
+                        tr.translation = resource.calc_center(*pos);
+                    }
+                }
+            }
+        }
+        *victory = Victory::default();
+    }
+
+    for event in solved.iter() {
+        let start = match *mode {
+            PlayMode::Demo => continue,
+            PlayMode::Manual => log.game.as_ref(),
+            PlayMode::Replay => replay.game.as_ref(),
+        };
+        let optimal = start.and_then(|game| {
+            // A line of cubes shifted by one click counts as one move.
+            let plan = Solver::with_goal(&board.distances, &board.goal)
+                .with_metric(Metric::Line)
+                .solve(&game.start, OPTIMAL_BUDGET);
+            plan.solved.then_some(plan.moves.len())
+        });
+        info!(
+            "Solved in {} moves (optimal {:?}), {:.1} s",
+            event.moves, optimal, event.seconds
+        );
+        victory.summary = Some(Summary {
+            moves: event.moves,
+            seconds: event.seconds,
+            optimal,
+        });
+        victory.phase = Phase::Waiting;
+    }
+
+    match victory.phase {
+        Phase::Waiting if animation.pending() == 0 => victory.phase = Phase::Hopping(0.0),
+        Phase::Hopping(elapsed) => {
+            let elapsed = elapsed + time.delta_seconds();
+            let height = Victory::HEIGHT * resource.cube_size;
+            let hole = board.hole();
+            let mut last = 0.0_f32;
+            for (pos, cube) in board.board.parts() {
+                let delay = hole
+                    .and_then(|hole| board.distances.get(hole, *pos))
+                    .map_or(0.0, |steps| steps as f32 * Victory::STAGGER);
+                last = last.max(delay);
+                let t = ((elapsed - delay) / Victory::HOP).clamp(0.0, 1.0);
+                let lift = if t < 1.0 { (PI * t).sin() } else { 0.0 };
+                let entity = board.entities.get(&cube.home());
+                if let Some(mut tr) = entity.and_then(|entity| query.get_mut(*entity).ok()) {
+                    tr.translation =
+                        resource.calc_center(*pos) + outward(*pos, resource.spaces) * (lift * height);
+                }
+            }
+            victory.phase = if elapsed >= last + Victory::HOP {
+                Phase::Done
+            } else {
+                Phase::Hopping(elapsed)
+            };
+        }
+        _ => {}
+    }
+}
+
+/// Away from the box, out through every face the cell lies on.
+fn outward(pos: Pos3D, spaces: Size3D) -> Vec3 {
+    pos.get_faces(spaces)
+        .into_iter()
+        .map(unit_vec)
+        .fold(Vec3::ZERO, |sum, v| sum + v)
+        .normalize_or_zero()
+}
+
+#[derive(Debug, Clone, Copy, Component)]
+pub struct VictoryText;
+
+pub fn setup(mut commands: Commands, font: Res<UiFont>) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Percent(40.0),
+                    left: Val::Percent(35.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section("", font.style(32.0), Default::default()),
+            ..Default::default()
+        })
+        .insert(VictoryText);
+}
+
+pub fn show(victory: Res<Victory>, mut query: Query<&mut Text, With<VictoryText>>) {
+    if !victory.is_changed() {
+        return;
+    }
+    let value = match (victory.phase, victory.summary) {
+        (Phase::Waiting | Phase::Hopping(_), _) => "Solved!".to_string(),
+        (Phase::Done, Some(summary)) => {
+            let optimal = match summary.optimal {
+                Some(optimal) => format!("optimal {}", optimal),
+                None => "optimal unknown".to_string(),
+            };
+            format!(
+                "Solved!\nMoves {} ({})\nTime  {:02}:{:04.1}",
+                summary.moves,
+                optimal,
+                (summary.seconds / 60.0) as u32,
+                summary.seconds % 60.0,
+            )
+        }
+        _ => String::new(),
+    };
+    for mut text in query.iter_mut() {
+        text.sections[0].value = value.clone();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::harness::Harness;
+    use alignment::board::Move;
+
+    /// How far each body stands from the center of its cell.
+    fn lifts(harness: &mut Harness) -> Vec<f32> {
+        let bodies = harness.bodies();
+        let resource = harness.resource::<CubesResource>();
+        bodies
+            .iter()
+            .map(|(_, pos, tr)| tr.translation.distance(resource.calc_center(*pos)))
+            .collect()
+    }
+
+    #[test]
+    fn solving_is_celebrated() {
+        let mut harness = Harness::manual();
+        let hole = harness.board().hole().unwrap();
+        let mut scrambled = harness.board().board.clone();
+        let mv = scrambled.moves()[0];
+        assert!(scrambled.apply(mv));
+        let spaces = scrambled.size();
+        harness.resource_mut::<BoardResource>().queued = Some((scrambled, 7));
+        harness.resource_mut::<CubesResource>().spaces = spaces;
+        harness.advance(0.1);
+        assert!(!harness.board().is_solved());
+
+        assert!(harness.slide(Move::new(hole, mv.direction.invert())));
+        harness.advance(0.1);
+        let victory = harness.resource::<Victory>();
+        assert_eq!(victory.phase, Phase::Waiting);
+        assert_eq!(
+            victory.summary,
+            Some(Summary {
+                moves: 1,
+                seconds: 0.0,
+                optimal: Some(1),
+            })
+        );
+
+        let mut highest = 0.0_f32;
+        for _ in 0..40 {
+            harness.advance(0.1);
+            highest = lifts(&mut harness).into_iter().fold(highest, f32::max);
+        }
+        assert!(highest > 0.3);
+        assert_eq!(harness.resource::<Victory>().phase, Phase::Done);
+        assert!(lifts(&mut harness).into_iter().all(|lift| lift == 0.0));
+    }
+}